@@ -0,0 +1,87 @@
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+/// Errors of the renderer and of image encoding.
+///
+/// Converts into the matching [`brul_utils::Error`] variant, so it can be propagated with `?`
+/// from code returning [`brul_utils::Result`].
+#[derive(Error, Debug)]
+pub enum RenderError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    WgpuRequestAdapter(#[from] wgpu::RequestAdapterError),
+
+    #[error(transparent)]
+    WgpuRequestDevice(#[from] wgpu::RequestDeviceError),
+
+    #[error(transparent)]
+    WgpuCreateSurface(#[from] wgpu::CreateSurfaceError),
+
+    #[error("surface is not supported by the GPU adapter")]
+    SurfaceUnsupported,
+
+    #[error("out of memory while acquiring the next frame")]
+    SurfaceOutOfMemory,
+
+    #[error("GPU device lost and could not be recreated: {0}")]
+    DeviceLost(Box<RenderError>),
+
+    #[error(transparent)]
+    WgpuBufferAsync(#[from] wgpu::BufferAsyncError),
+
+    #[error(transparent)]
+    WgpuPoll(#[from] wgpu::PollError),
+
+    #[error("render target can not be read back")]
+    UnreadableTarget,
+
+    #[error("GPU dropped the readback before mapping the buffer")]
+    ReadbackAborted,
+
+    #[error("{len} bytes are not a {width}x{height} RGBA image")]
+    InvalidImageSize { width: u32, height: u32, len: usize },
+
+    #[error(transparent)]
+    PngEncoding(#[from] png::EncodingError),
+
+    #[error(transparent)]
+    PngDecoding(#[from] png::DecodingError),
+
+    #[error("unsupported image color type {0:?}")]
+    UnsupportedImage(png::ColorType),
+
+    #[error("golden image {} does not exist, set BRUL_UPDATE_GOLDEN to create it", .0.display())]
+    MissingGolden(PathBuf),
+}
+
+pub type Result<T> = std::result::Result<T, RenderError>;
+
+impl From<RenderError> for brul_utils::Error {
+    fn from(err: RenderError) -> Self {
+        use brul_utils::Error;
+        let message = err.to_string();
+        match err {
+            RenderError::Io(err) => Error::Io(err),
+            RenderError::WgpuRequestAdapter(_) | RenderError::WgpuRequestDevice(_) => {
+                Error::AdapterUnavailable(message)
+            }
+            RenderError::DeviceLost(err) => Error::DeviceLost(err.to_string()),
+            RenderError::WgpuCreateSurface(_) | RenderError::SurfaceUnsupported => {
+                Error::SurfaceUnrecoverable(message)
+            }
+            RenderError::SurfaceOutOfMemory => Error::GpuOutOfMemory,
+            RenderError::WgpuBufferAsync(_)
+            | RenderError::WgpuPoll(_)
+            | RenderError::UnreadableTarget
+            | RenderError::ReadbackAborted => Error::Readback(message),
+            RenderError::PngEncoding(_) => Error::ImageEncode(message),
+            RenderError::PngDecoding(_)
+            | RenderError::UnsupportedImage(_)
+            | RenderError::InvalidImageSize { .. } => Error::ImageDecode(message),
+            RenderError::MissingGolden(path) => Error::MissingGolden(path),
+        }
+    }
+}
@@ -0,0 +1,597 @@
+use crate::{
+    RenderError,
+    image::Image,
+    input::{InputState, ime_input},
+    renderer::Renderer,
+};
+use brul_utils::{
+    AppControlMessage, CursorIcon, Error, GuiControlMessage, GuiProxy, GuiRequest, Point, Result,
+    Size, WindowConfig, WindowId, WindowSize, WindowUpdate,
+    input::{
+        ClickTiming, ImeEvent, InputEvent, Key, Modifiers, NamedKey, ScrollDelta,
+        us_layout_character,
+    },
+};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    path::PathBuf,
+    sync::mpsc,
+    time::{Duration, Instant},
+};
+use winit::{dpi::PhysicalSize, event::MouseButton, keyboard::KeyCode};
+
+/// Input fed into the headless backend in place of OS window events.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyntheticEvent {
+    CloseRequested,
+    Resized {
+        width: u32,
+        height: u32,
+    },
+    ScaleFactorChanged(f64),
+    /// Key press on a US layout; the logical key is derived from the code.
+    KeyPressed(KeyCode),
+    KeyReleased(KeyCode),
+    ModifiersChanged(Modifiers),
+    /// Typed text, as delivered after a key press.
+    Text(String),
+    /// Input method event; dropped unless the window allows IME input, like on real platforms.
+    Ime(ImeEvent),
+    /// Cursor position in physical pixels.
+    CursorMoved {
+        x: f64,
+        y: f64,
+    },
+    CursorEntered,
+    CursorLeft,
+    MouseInput {
+        button: MouseButton,
+        pressed: bool,
+    },
+    /// Mouse wheel notches.
+    Scroll {
+        x: f32,
+        y: f32,
+    },
+    Focused(bool),
+    FileDropped(PathBuf),
+}
+
+/// Virtual window: a size and an offscreen renderer, if an adapter was available.
+struct HeadlessWindow {
+    config: WindowConfig,
+    size: WindowSize,
+    cursor: CursorIcon,
+    cursor_visible: bool,
+    ime_cursor_area: Option<(Point, Size)>,
+    input: InputState,
+    renderer: Option<Renderer>,
+}
+
+impl HeadlessWindow {
+    fn new(id: WindowId, config: &WindowConfig, click_timing: ClickTiming) -> Self {
+        let size = WindowSize::new(
+            PhysicalSize::new(
+                config.inner_size.width.round() as u32,
+                config.inner_size.height.round() as u32,
+            ),
+            1.0,
+        );
+        let renderer = match pollster::block_on(Renderer::new_offscreen(size.width, size.height)) {
+            Ok(renderer) => Some(renderer),
+            Err(err) => {
+                tracing::warn!(
+                    "Headless renderer for {id:?} unavailable, frames are skipped: {err}"
+                );
+                None
+            }
+        };
+        Self {
+            config: config.clone(),
+            size,
+            cursor: CursorIcon::Default,
+            cursor_visible: true,
+            ime_cursor_area: None,
+            input: InputState::new(click_timing),
+            renderer,
+        }
+    }
+
+    fn resize(&mut self, physical: PhysicalSize<u32>) {
+        self.size = WindowSize::new(physical, self.size.scale_factor);
+        if let Some(renderer) = self.renderer.as_mut() {
+            renderer.resize(physical);
+        }
+    }
+}
+
+/// GUI backend without a display server.
+///
+/// Follows the same lifecycle as [`crate::GuiBackend`] (resumed, window events, user events,
+/// frames), but renders into offscreen textures. Tests can drive it manually with
+/// [`HeadlessBackend::inject`] and [`HeadlessBackend::step`], then end it with
+/// [`HeadlessBackend::finish`]; [`HeadlessBackend::run`] paces frames in real time like the
+/// windowed event loop.
+#[non_exhaustive]
+pub struct HeadlessBackend {
+    gui_tx: mpsc::Sender<GuiControlMessage>,
+    gui_rx: mpsc::Receiver<GuiControlMessage>,
+    app_tx: mpsc::Sender<AppControlMessage>,
+    windows: BTreeMap<WindowId, HeadlessWindow>,
+    main_window: WindowConfig,
+    click_timing: ClickTiming,
+    input: VecDeque<(WindowId, SyntheticEvent)>,
+    frame: u64,
+    resumed: bool,
+    exiting: bool,
+    error: Option<Error>,
+}
+
+impl HeadlessBackend {
+    pub fn new(app_tx: mpsc::Sender<AppControlMessage>, main_window: WindowConfig) -> Result<Self> {
+        let (gui_tx, gui_rx) = mpsc::channel::<GuiControlMessage>();
+        Ok(Self {
+            gui_tx,
+            gui_rx,
+            app_tx,
+            windows: BTreeMap::new(),
+            main_window,
+            click_timing: ClickTiming::default(),
+            input: VecDeque::new(),
+            frame: 0,
+            resumed: false,
+            exiting: false,
+            error: None,
+        })
+    }
+
+    /// Timing used to detect double and triple clicks in every window.
+    pub fn with_click_timing(mut self, click_timing: ClickTiming) -> Self {
+        self.click_timing = click_timing;
+        self
+    }
+
+    pub fn get_proxy(&self) -> GuiProxy {
+        GuiProxy::Headless(self.gui_tx.clone())
+    }
+
+    /// Queues a synthetic event for `window`, delivered on the next [`HeadlessBackend::step`].
+    pub fn inject(&mut self, window: WindowId, event: SyntheticEvent) {
+        self.input.push_back((window, event));
+    }
+
+    /// Queues an IME composition for `window` the way platforms deliver it: one preedit per
+    /// entry of `preedits`, then the preedit is cleared and `commit` is committed.
+    pub fn inject_composition(&mut self, window: WindowId, preedits: &[&str], commit: &str) {
+        for text in preedits {
+            let cursor = Some((text.len(), text.len()));
+            self.inject(
+                window,
+                SyntheticEvent::Ime(ImeEvent::Preedit {
+                    text: text.to_string(),
+                    cursor,
+                }),
+            );
+        }
+        self.inject(
+            window,
+            SyntheticEvent::Ime(ImeEvent::Preedit {
+                text: String::new(),
+                cursor: None,
+            }),
+        );
+        self.inject(
+            window,
+            SyntheticEvent::Ime(ImeEvent::Commit(commit.to_owned())),
+        );
+    }
+
+    /// Ids of all open virtual windows.
+    pub fn windows(&self) -> Vec<WindowId> {
+        self.windows.keys().copied().collect()
+    }
+
+    pub fn renderer(&self, window: WindowId) -> Option<&Renderer> {
+        self.windows.get(&window)?.renderer.as_ref()
+    }
+
+    /// Reads back the last rendered frame of `window`.
+    pub fn capture(&self, window: WindowId) -> Result<Image> {
+        let renderer = self.renderer(window).ok_or(RenderError::UnreadableTarget)?;
+        Ok(renderer.read_pixels()?)
+    }
+
+    /// Config of a virtual window, including runtime updates applied to it.
+    pub fn config(&self, window: WindowId) -> Option<&WindowConfig> {
+        self.windows.get(&window).map(|window| &window.config)
+    }
+
+    /// Current size of a virtual window.
+    pub fn size(&self, window: WindowId) -> Option<WindowSize> {
+        self.windows.get(&window).map(|window| window.size)
+    }
+
+    /// Cursor icon last requested for a virtual window.
+    pub fn cursor(&self, window: WindowId) -> Option<CursorIcon> {
+        self.windows.get(&window).map(|window| window.cursor)
+    }
+
+    /// Whether the cursor of a virtual window is visible.
+    pub fn cursor_visible(&self, window: WindowId) -> Option<bool> {
+        self.windows
+            .get(&window)
+            .map(|window| window.cursor_visible)
+    }
+
+    /// IME cursor area last set for a virtual window, in logical pixels.
+    pub fn ime_cursor_area(&self, window: WindowId) -> Option<(Point, Size)> {
+        self.windows.get(&window)?.ime_cursor_area
+    }
+
+    /// Number of frames rendered so far.
+    pub fn frame_count(&self) -> u64 {
+        self.frame
+    }
+
+    pub fn is_exiting(&self) -> bool {
+        self.exiting
+    }
+
+    pub fn resumed(&mut self) {
+        if self.resumed {
+            return;
+        }
+        self.resumed = true;
+        self.send_app_message(AppControlMessage::Resumed);
+
+        let config = self.main_window.clone();
+        self.create_window(WindowId::MAIN, &config);
+    }
+
+    /// Runs exactly one iteration: pending control messages, queued input, then one frame.
+    ///
+    /// Returns `false` once the backend was asked to exit or hit an unrecoverable error.
+    pub fn step(&mut self) -> bool {
+        self.resumed();
+
+        while let Ok(message) = self.gui_rx.try_recv() {
+            self.user_event(message);
+        }
+
+        while let Some((window, event)) = self.input.pop_front() {
+            self.window_event(window, event);
+        }
+
+        if !self.exiting {
+            self.redraw();
+        }
+
+        !self.exiting
+    }
+
+    pub fn run(mut self) -> Result<()> {
+        const FRAME_DURATION: Duration = Duration::from_nanos(16_666_667);
+
+        let mut next_frame_time = Instant::now();
+        loop {
+            let timeout = next_frame_time.saturating_duration_since(Instant::now());
+            match self.gui_rx.recv_timeout(timeout) {
+                Ok(message) => {
+                    self.user_event(message);
+                    if self.exiting {
+                        break;
+                    }
+                    // Keep rendering while messages keep coming in.
+                    if Instant::now() < next_frame_time {
+                        continue;
+                    }
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) => {}
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            }
+
+            if !self.step() {
+                break;
+            }
+
+            let now = Instant::now();
+            while now >= next_frame_time {
+                next_frame_time += FRAME_DURATION;
+            }
+        }
+
+        self.finish()
+    }
+
+    /// Ends a manually driven backend: handles the pending control messages, closes the
+    /// windows still open and returns the error that stopped the backend, if any.
+    pub fn finish(mut self) -> Result<()> {
+        while let Ok(message) = self.gui_rx.try_recv() {
+            self.user_event(message);
+        }
+        if !self.exiting {
+            self.shutdown();
+        }
+
+        match self.error.take() {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    fn create_window(&mut self, id: WindowId, config: &WindowConfig) -> WindowSize {
+        if let Some(window) = self.windows.get(&id) {
+            return window.size;
+        }
+
+        let window = HeadlessWindow::new(id, config, self.click_timing);
+        let size = window.size;
+        self.windows.insert(id, window);
+        self.send_app_message(AppControlMessage::WindowCreated(id));
+        self.send_app_message(AppControlMessage::WindowResized { window: id, size });
+        if config.ime_allowed {
+            let mut events = Vec::new();
+            ime_input(ImeEvent::Enabled, |event| events.push(event));
+            self.send_input(id, events);
+        }
+        size
+    }
+
+    fn window_mut(&mut self, id: WindowId) -> Result<&mut HeadlessWindow> {
+        self.windows.get_mut(&id).ok_or(Error::WindowNotFound(id))
+    }
+
+    fn handle_request(&mut self, request: GuiRequest) {
+        match request {
+            GuiRequest::CreateWindow { id, config, reply } => {
+                let _ = reply.send(Ok(self.create_window(id, &config)));
+            }
+            GuiRequest::SetTitle { id, title, reply } => {
+                let result = self
+                    .window_mut(id)
+                    .map(|window| window.config.title = title);
+                let _ = reply.send(result);
+            }
+            GuiRequest::SetCursor { id, cursor, reply } => {
+                let result = self.window_mut(id).map(|window| window.cursor = cursor);
+                let _ = reply.send(result);
+            }
+            GuiRequest::RequestRedraw { id, reply } => {
+                // Every step renders all windows, so an existing window is redrawn anyway.
+                let _ = reply.send(self.window_mut(id).map(|_| ()));
+            }
+            GuiRequest::WindowSize { id, reply } => {
+                let _ = reply.send(self.window_mut(id).map(|window| window.size));
+            }
+        }
+    }
+
+    /// Like [`crate::GuiBackend`], closing the main or the last window only asks the app to
+    /// exit; the window is closed once the app confirms with [`GuiControlMessage::Shutdown`].
+    fn close_window(&mut self, id: WindowId) {
+        if !self.windows.contains_key(&id) {
+            return;
+        }
+        if id == WindowId::MAIN || self.windows.len() == 1 {
+            self.request_exit();
+        } else {
+            self.remove_window(id);
+        }
+    }
+
+    fn remove_window(&mut self, id: WindowId) {
+        if self.windows.remove(&id).is_some() {
+            self.send_app_message(AppControlMessage::WindowClosed(id));
+        }
+    }
+
+    /// Closes the remaining windows once the app confirmed the exit.
+    fn shutdown(&mut self) {
+        let ids: Vec<_> = self.windows.keys().copied().collect();
+        for id in ids {
+            self.remove_window(id);
+        }
+        self.exiting = true;
+    }
+
+    /// Lets the app decide whether to exit; exits right away if the app is gone.
+    fn request_exit(&mut self) {
+        if self
+            .app_tx
+            .send(AppControlMessage::RequestShutdown)
+            .is_err()
+        {
+            self.exiting = true;
+        }
+    }
+
+    fn update_window(&mut self, id: WindowId, update: WindowUpdate) {
+        let Some(window) = self.windows.get_mut(&id) else {
+            return;
+        };
+        let ime_allowed = window.config.ime_allowed;
+        window.config.apply(&update);
+
+        let mut events = Vec::new();
+        match update {
+            WindowUpdate::InnerSize(size) => {
+                let scale_factor = window.size.scale_factor;
+                let physical = winit::dpi::LogicalSize::new(size.width, size.height)
+                    .to_physical::<u32>(scale_factor);
+                window.resize(physical);
+                let size = window.size;
+                self.send_app_message(AppControlMessage::WindowResized { window: id, size });
+            }
+            WindowUpdate::ImeAllowed(true) if !ime_allowed => {
+                ime_input(ImeEvent::Enabled, |event| events.push(event))
+            }
+            WindowUpdate::ImeAllowed(false) if ime_allowed => {
+                ime_input(ImeEvent::Disabled, |event| events.push(event))
+            }
+            WindowUpdate::ImeCursorArea { position, size } => {
+                window.ime_cursor_area = Some((position, size));
+            }
+            WindowUpdate::CursorVisible(visible) => window.cursor_visible = visible,
+            WindowUpdate::PointerCapture(captured) => window
+                .input
+                .set_capture(captured, |event| events.push(event)),
+            _ => {}
+        }
+        self.send_input(id, events);
+    }
+
+    fn window_event(&mut self, id: WindowId, event: SyntheticEvent) {
+        self.input(id, &event);
+        let Some(window) = self.windows.get_mut(&id) else {
+            return;
+        };
+
+        match event {
+            SyntheticEvent::CloseRequested => {
+                self.close_window(id);
+            }
+            SyntheticEvent::Resized { width, height } => {
+                window.resize(PhysicalSize::new(width, height));
+                let size = window.size;
+                self.send_app_message(AppControlMessage::WindowResized { window: id, size });
+            }
+            SyntheticEvent::ScaleFactorChanged(scale_factor) => {
+                window.size.scale_factor = scale_factor;
+                let size = window.size;
+                self.send_app_message(AppControlMessage::ScaleFactorChanged { window: id, size });
+            }
+            event => {
+                tracing::trace!("Synthetic event for {:?}: {:?}", id, event);
+            }
+        }
+    }
+
+    /// Forwards the input part of a synthetic event, like the windowed backend does for
+    /// winit events.
+    fn input(&mut self, id: WindowId, event: &SyntheticEvent) {
+        let Some(window) = self.windows.get_mut(&id) else {
+            return;
+        };
+        let input = &mut window.input;
+        let mut events = Vec::new();
+        let mut emit = |event| events.push(event);
+        match event {
+            SyntheticEvent::KeyPressed(code) => {
+                emit(input.key(logical_key(*code), Some(*code), true, false))
+            }
+            SyntheticEvent::KeyReleased(code) => {
+                emit(input.key(logical_key(*code), Some(*code), false, false))
+            }
+            SyntheticEvent::ModifiersChanged(modifiers) => {
+                emit(input.modifiers_changed(*modifiers))
+            }
+            SyntheticEvent::Text(text) => emit(InputEvent::Text(text.clone())),
+            SyntheticEvent::CursorMoved { x, y } => {
+                let scale_factor = window.size.scale_factor;
+                emit(input.cursor_moved(Point::new(
+                    (x / scale_factor) as f32,
+                    (y / scale_factor) as f32,
+                )))
+            }
+            SyntheticEvent::CursorEntered => input.entered(emit),
+            SyntheticEvent::CursorLeft => input.left(emit),
+            SyntheticEvent::MouseInput { button, pressed } => {
+                input.button((*button).into(), *pressed, emit)
+            }
+            SyntheticEvent::Scroll { x, y } => {
+                emit(input.scroll(ScrollDelta::Lines { x: *x, y: *y }))
+            }
+            SyntheticEvent::Focused(focused) => input.focused(*focused, emit),
+            SyntheticEvent::FileDropped(path) => emit(InputEvent::FileDropped(path.clone())),
+            SyntheticEvent::Ime(event) => {
+                if window.config.ime_allowed {
+                    ime_input(event.clone(), emit);
+                } else {
+                    tracing::trace!("IME event for {:?} dropped, IME is not allowed", id);
+                }
+            }
+            SyntheticEvent::CloseRequested
+            | SyntheticEvent::Resized { .. }
+            | SyntheticEvent::ScaleFactorChanged(_) => {}
+        }
+        self.send_input(id, events);
+    }
+
+    fn send_input(&self, id: WindowId, events: Vec<InputEvent>) {
+        for event in events {
+            self.send_app_message(AppControlMessage::Input { window: id, event });
+        }
+    }
+
+    fn send_app_message(&self, message: AppControlMessage) {
+        if self.app_tx.send(message).is_err() {
+            tracing::error!("Send message error");
+        }
+    }
+
+    fn user_event(&mut self, event: GuiControlMessage) {
+        match event {
+            GuiControlMessage::Shutdown => {
+                self.shutdown();
+            }
+            GuiControlMessage::CloseWindow(id) => {
+                self.close_window(id);
+            }
+            GuiControlMessage::UpdateWindow { id, update } => {
+                self.update_window(id, update);
+            }
+            GuiControlMessage::Request(request) => {
+                self.handle_request(request);
+            }
+        }
+    }
+
+    fn redraw(&mut self) {
+        tracing::trace!("RedrawRequested");
+        for window in self.windows.values_mut() {
+            let Some(renderer) = window.renderer.as_mut() else {
+                continue;
+            };
+            if let Err(err) = renderer.clear(window.config.background_color) {
+                tracing::error!("Unrecoverable GUI error: {err}");
+                self.error = Some(err.into());
+                self.exiting = true;
+                break;
+            }
+        }
+        self.frame += 1;
+    }
+}
+
+/// Logical key for `code` on a US layout, enough to exercise shortcuts and text handling.
+fn logical_key(code: KeyCode) -> Key {
+    match code {
+        KeyCode::Escape => Key::Named(NamedKey::Escape),
+        KeyCode::Enter | KeyCode::NumpadEnter => Key::Named(NamedKey::Enter),
+        KeyCode::Tab => Key::Named(NamedKey::Tab),
+        KeyCode::Backspace => Key::Named(NamedKey::Backspace),
+        KeyCode::Delete => Key::Named(NamedKey::Delete),
+        KeyCode::Space => Key::Named(NamedKey::Space),
+        KeyCode::ArrowLeft => Key::Named(NamedKey::ArrowLeft),
+        KeyCode::ArrowRight => Key::Named(NamedKey::ArrowRight),
+        KeyCode::ArrowUp => Key::Named(NamedKey::ArrowUp),
+        KeyCode::ArrowDown => Key::Named(NamedKey::ArrowDown),
+        KeyCode::Home => Key::Named(NamedKey::Home),
+        KeyCode::End => Key::Named(NamedKey::End),
+        KeyCode::ShiftLeft | KeyCode::ShiftRight => Key::Named(NamedKey::Shift),
+        KeyCode::ControlLeft | KeyCode::ControlRight => Key::Named(NamedKey::Control),
+        KeyCode::AltLeft | KeyCode::AltRight => Key::Named(NamedKey::Alt),
+        KeyCode::SuperLeft | KeyCode::SuperRight => Key::Named(NamedKey::Super),
+        KeyCode::F1 => Key::Named(NamedKey::F1),
+        KeyCode::F2 => Key::Named(NamedKey::F2),
+        KeyCode::F3 => Key::Named(NamedKey::F3),
+        KeyCode::F4 => Key::Named(NamedKey::F4),
+        KeyCode::F5 => Key::Named(NamedKey::F5),
+        KeyCode::F11 => Key::Named(NamedKey::F11),
+        KeyCode::F12 => Key::Named(NamedKey::F12),
+        code => match us_layout_character(code) {
+            Some(c) => Key::Character(c.to_string()),
+            None => Key::Unidentified,
+        },
+    }
+}
@@ -0,0 +1,144 @@
+use crate::error::{RenderError, Result};
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::Path,
+};
+
+/// Environment variable that makes [`Image::compare_golden`] overwrite golden files.
+pub const UPDATE_GOLDEN_ENV: &str = "BRUL_UPDATE_GOLDEN";
+
+/// Tightly packed 8-bit RGBA pixels, rows top to bottom.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+/// Result of comparing two images pixel by pixel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageDiff {
+    /// Pixels where any channel differs by more than the tolerance.
+    pub differing_pixels: usize,
+    /// Largest per-channel difference found.
+    pub max_difference: u8,
+    /// `false` when the images have different dimensions.
+    pub same_size: bool,
+}
+
+impl ImageDiff {
+    pub fn is_match(&self) -> bool {
+        self.same_size && self.differing_pixels == 0
+    }
+}
+
+impl Image {
+    /// Fails with [`RenderError::InvalidImageSize`] unless `pixels` holds exactly
+    /// `width * height` RGBA pixels.
+    pub fn new(width: u32, height: u32, pixels: Vec<u8>) -> Result<Self> {
+        if pixels.len() != width as usize * height as usize * 4 {
+            return Err(RenderError::InvalidImageSize {
+                width,
+                height,
+                len: pixels.len(),
+            });
+        }
+        Ok(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let offset = (y as usize * self.width as usize + x as usize) * 4;
+        let mut pixel = [0; 4];
+        pixel.copy_from_slice(&self.pixels[offset..offset + 4]);
+        pixel
+    }
+
+    pub fn save_png(&self, path: impl AsRef<Path>) -> Result<()> {
+        let file = File::create(path)?;
+        let mut encoder = png::Encoder::new(BufWriter::new(file), self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.pixels)?;
+        writer.finish()?;
+        Ok(())
+    }
+
+    pub fn load_png(path: impl AsRef<Path>) -> Result<Self> {
+        let file = File::open(path)?;
+        let mut decoder = png::Decoder::new(BufReader::new(file));
+        decoder.set_transformations(
+            png::Transformations::normalize_to_color8() | png::Transformations::ALPHA,
+        );
+        let mut reader = decoder.read_info()?;
+        let mut pixels = vec![0; reader.output_buffer_size().unwrap_or_default()];
+        let info = reader.next_frame(&mut pixels)?;
+        if info.color_type != png::ColorType::Rgba {
+            return Err(RenderError::UnsupportedImage(info.color_type));
+        }
+        pixels.truncate(info.buffer_size());
+        Self::new(info.width, info.height, pixels)
+    }
+
+    /// Compares pixels, ignoring per-channel differences up to `tolerance`.
+    pub fn diff(&self, other: &Image, tolerance: u8) -> ImageDiff {
+        if self.width != other.width || self.height != other.height {
+            return ImageDiff {
+                differing_pixels: self.pixels.len().max(other.pixels.len()) / 4,
+                max_difference: u8::MAX,
+                same_size: false,
+            };
+        }
+
+        let mut diff = ImageDiff {
+            differing_pixels: 0,
+            max_difference: 0,
+            same_size: true,
+        };
+        for (a, b) in self.pixels.chunks_exact(4).zip(other.pixels.chunks_exact(4)) {
+            let max = a.iter().zip(b).map(|(a, b)| a.abs_diff(*b)).max().unwrap_or(0);
+            diff.max_difference = diff.max_difference.max(max);
+            if max > tolerance {
+                diff.differing_pixels += 1;
+            }
+        }
+        diff
+    }
+
+    /// Compares against a checked-in golden PNG.
+    ///
+    /// A missing golden is an error. With [`UPDATE_GOLDEN_ENV`] set the golden is (re)written
+    /// instead, and the comparison then trivially matches.
+    pub fn compare_golden(&self, path: impl AsRef<Path>, tolerance: u8) -> Result<ImageDiff> {
+        let path = path.as_ref();
+        if std::env::var_os(UPDATE_GOLDEN_ENV).is_some() {
+            tracing::info!("Writing golden image {}", path.display());
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            self.save_png(path)?;
+        } else if !path.exists() {
+            return Err(RenderError::MissingGolden(path.to_owned()));
+        }
+
+        let golden = Image::load_png(path)?;
+        Ok(self.diff(&golden, tolerance))
+    }
+}
@@ -0,0 +1,254 @@
+use brul_utils::{
+    Point,
+    input::{
+        ClickTiming, ImeEvent, InputEvent, Key, KeyCode, KeyEvent, Modifiers, PointerButton,
+        PointerButtons, PointerEvent, ScrollDelta,
+    },
+};
+use std::time::Instant;
+use winit::{
+    event::{ElementState, MouseScrollDelta, WindowEvent},
+    keyboard::PhysicalKey,
+};
+
+/// Pointer, button and modifier state of one window, used to fill in the parts of an
+/// [`InputEvent`] the raw backend event does not carry.
+#[derive(Debug, Default)]
+pub(crate) struct InputState {
+    position: Point,
+    buttons: PointerButtons,
+    modifiers: Modifiers,
+    /// Whether the pointer is physically over the window.
+    inside: bool,
+    /// Whether the app was told the pointer is over the window.
+    hovered: bool,
+    captured: bool,
+    click_timing: ClickTiming,
+    last_click: Option<Click>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Click {
+    button: PointerButton,
+    position: Point,
+    time: Instant,
+    count: u32,
+}
+
+impl InputState {
+    pub(crate) fn new(click_timing: ClickTiming) -> Self {
+        Self {
+            click_timing,
+            ..Default::default()
+        }
+    }
+
+    fn pointer(&self) -> PointerEvent {
+        PointerEvent {
+            position: self.position,
+            buttons: self.buttons,
+            modifiers: self.modifiers,
+        }
+    }
+
+    pub(crate) fn cursor_moved(&mut self, position: Point) -> InputEvent {
+        self.position = position;
+        InputEvent::PointerMoved(self.pointer())
+    }
+
+    pub(crate) fn entered(&mut self, mut emit: impl FnMut(InputEvent)) {
+        self.inside = true;
+        if !self.hovered {
+            self.hovered = true;
+            emit(InputEvent::PointerEntered);
+        }
+    }
+
+    pub(crate) fn left(&mut self, mut emit: impl FnMut(InputEvent)) {
+        self.inside = false;
+        if self.hovered && !self.captured {
+            self.hovered = false;
+            emit(InputEvent::PointerLeft);
+        }
+    }
+
+    pub(crate) fn button(
+        &mut self,
+        button: PointerButton,
+        pressed: bool,
+        mut emit: impl FnMut(InputEvent),
+    ) {
+        if pressed {
+            self.buttons.insert(button);
+            let count = self.click_count(button);
+            emit(InputEvent::PointerDown {
+                button,
+                pointer: self.pointer(),
+                count,
+            });
+        } else {
+            self.buttons.remove(button);
+            emit(InputEvent::PointerUp {
+                button,
+                pointer: self.pointer(),
+            });
+            if self.buttons.is_empty() {
+                self.set_capture(false, emit);
+            }
+        }
+    }
+
+    /// Starts or ends the pointer capture. Capture only starts while a button is pressed,
+    /// ending it reports a leave that happened in the meantime.
+    pub(crate) fn set_capture(&mut self, captured: bool, mut emit: impl FnMut(InputEvent)) {
+        let captured = captured && !self.buttons.is_empty();
+        if captured == self.captured {
+            return;
+        }
+        self.captured = captured;
+        emit(InputEvent::PointerCaptureChanged(captured));
+        if !captured && !self.inside {
+            self.left(emit);
+        }
+    }
+
+    fn click_count(&mut self, button: PointerButton) -> u32 {
+        let now = Instant::now();
+        let timing = self.click_timing;
+        let count = match self.last_click {
+            Some(last)
+                if last.button == button
+                    && now.duration_since(last.time) <= timing.interval
+                    && distance(last.position, self.position) <= timing.distance =>
+            {
+                last.count + 1
+            }
+            _ => 1,
+        };
+        self.last_click = Some(Click {
+            button,
+            position: self.position,
+            time: now,
+            count,
+        });
+        count
+    }
+
+    pub(crate) fn scroll(&self, delta: ScrollDelta) -> InputEvent {
+        InputEvent::Scroll {
+            delta,
+            pointer: self.pointer(),
+        }
+    }
+
+    pub(crate) fn modifiers_changed(&mut self, modifiers: Modifiers) -> InputEvent {
+        self.modifiers = modifiers;
+        InputEvent::ModifiersChanged(modifiers)
+    }
+
+    pub(crate) fn key(
+        &self,
+        key: Key,
+        physical_key: Option<KeyCode>,
+        pressed: bool,
+        repeat: bool,
+    ) -> InputEvent {
+        let event = KeyEvent {
+            key,
+            physical_key,
+            modifiers: self.modifiers,
+            repeat,
+        };
+        if pressed {
+            InputEvent::KeyDown(event)
+        } else {
+            InputEvent::KeyUp(event)
+        }
+    }
+
+    pub(crate) fn focused(&mut self, focused: bool, mut emit: impl FnMut(InputEvent)) {
+        if !focused {
+            // Releases happening in other windows are never reported.
+            self.buttons = PointerButtons::default();
+            self.set_capture(false, &mut emit);
+        }
+        emit(InputEvent::Focused(focused));
+    }
+
+    /// Translates a winit window event, calling `emit` for each resulting input event.
+    pub(crate) fn translate(
+        &mut self,
+        event: &WindowEvent,
+        scale_factor: f64,
+        mut emit: impl FnMut(InputEvent),
+    ) {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                let position = position.to_logical::<f32>(scale_factor);
+                emit(self.cursor_moved(Point::new(position.x, position.y)));
+            }
+            WindowEvent::CursorEntered { .. } => self.entered(emit),
+            WindowEvent::CursorLeft { .. } => self.left(emit),
+            WindowEvent::MouseInput { state, button, .. } => {
+                self.button((*button).into(), *state == ElementState::Pressed, emit);
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let delta = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => ScrollDelta::Lines { x: *x, y: *y },
+                    MouseScrollDelta::PixelDelta(delta) => {
+                        let delta = delta.to_logical::<f32>(scale_factor);
+                        ScrollDelta::Pixels {
+                            x: delta.x,
+                            y: delta.y,
+                        }
+                    }
+                };
+                emit(self.scroll(delta));
+            }
+            WindowEvent::KeyboardInput { event, .. } => {
+                let physical_key = match event.physical_key {
+                    PhysicalKey::Code(code) => Some(code),
+                    PhysicalKey::Unidentified(_) => None,
+                };
+                let pressed = event.state == ElementState::Pressed;
+                emit(self.key(
+                    Key::from(&event.logical_key),
+                    physical_key,
+                    pressed,
+                    event.repeat,
+                ));
+                if let Some(text) = event.text.as_ref().filter(|_| pressed) {
+                    // Enter, Backspace, Escape and friends are keys, not text.
+                    if !text.chars().all(char::is_control) {
+                        emit(InputEvent::Text(text.to_string()));
+                    }
+                }
+            }
+            WindowEvent::Ime(ime) => ime_input(ime.into(), emit),
+            WindowEvent::ModifiersChanged(modifiers) => {
+                emit(self.modifiers_changed(modifiers.state().into()));
+            }
+            WindowEvent::Focused(focused) => self.focused(*focused, emit),
+            WindowEvent::HoveredFile(path) => emit(InputEvent::FileHovered(path.clone())),
+            WindowEvent::HoveredFileCancelled => emit(InputEvent::FileHoverCancelled),
+            WindowEvent::DroppedFile(path) => emit(InputEvent::FileDropped(path.clone())),
+            _ => {}
+        }
+    }
+}
+
+/// Emits an IME event, followed by the committed text as a plain text input.
+pub(crate) fn ime_input(event: ImeEvent, mut emit: impl FnMut(InputEvent)) {
+    let text = match &event {
+        ImeEvent::Commit(text) => Some(text.clone()),
+        _ => None,
+    };
+    emit(InputEvent::Ime(event));
+    if let Some(text) = text {
+        emit(InputEvent::Text(text));
+    }
+}
+
+fn distance(a: Point, b: Point) -> f32 {
+    (a.x - b.x).hypot(a.y - b.y)
+}
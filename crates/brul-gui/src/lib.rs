@@ -1,128 +1,306 @@
-use crate::renderer::Renderer;
-use brul_utils::{AppControlMessage, Color, GuiControlMessage, Result};
+use crate::window::GuiWindow;
+use brul_utils::{
+    AppControlMessage, Config, Error, GuiControlMessage, GuiMode, GuiProxy, GuiRequest, Result,
+    WindowConfig, WindowId, WindowSize, WindowUpdate, input::ClickTiming,
+};
 use std::{
-    sync::{Arc, mpsc},
+    collections::HashMap,
+    sync::mpsc,
     time::{Duration, Instant},
 };
 use winit::{
     application::ApplicationHandler,
     event::WindowEvent,
-    event_loop::{self, ActiveEventLoop, ControlFlow, EventLoop, EventLoopProxy},
-    keyboard::{KeyCode, PhysicalKey},
-    window::{Window, WindowId},
+    event_loop::{ActiveEventLoop, ControlFlow, EventLoop, EventLoopProxy},
 };
 
-mod renderer;
+pub mod error;
+mod headless;
+pub mod image;
+mod input;
+pub mod renderer;
+mod window;
+
+pub use error::RenderError;
+pub use headless::{HeadlessBackend, SyntheticEvent};
+
+/// GUI backend selected by [`GuiMode`].
+pub enum Backend {
+    Windowed(Box<GuiBackend>),
+    Headless(Box<HeadlessBackend>),
+}
+
+impl Backend {
+    pub fn new(config: &Config, app_tx: mpsc::Sender<AppControlMessage>) -> Result<Self> {
+        let main_window = config.window().clone();
+        let click_timing = config.click_timing();
+        let backend = match config.gui_mode() {
+            GuiMode::Windowed => Backend::Windowed(Box::new(
+                GuiBackend::new(app_tx, main_window)?.with_click_timing(click_timing),
+            )),
+            GuiMode::Headless => Backend::Headless(Box::new(
+                HeadlessBackend::new(app_tx, main_window)?.with_click_timing(click_timing),
+            )),
+        };
+        Ok(backend)
+    }
+
+    pub fn get_proxy(&self) -> GuiProxy {
+        match self {
+            Backend::Windowed(backend) => backend.get_proxy(),
+            Backend::Headless(backend) => backend.get_proxy(),
+        }
+    }
+
+    pub fn run(self) -> Result<()> {
+        match self {
+            Backend::Windowed(backend) => backend.run(),
+            Backend::Headless(backend) => backend.run(),
+        }
+    }
+}
 
 #[non_exhaustive]
 pub struct GuiBackend {
     event_loop: Option<EventLoop<GuiControlMessage>>,
     event_loop_proxy: EventLoopProxy<GuiControlMessage>,
-    window: Option<Arc<Window>>,
-    renderer: Option<Renderer>,
+    windows: HashMap<winit::window::WindowId, GuiWindow>,
+    window_ids: HashMap<WindowId, winit::window::WindowId>,
+    main_window: WindowConfig,
+    click_timing: ClickTiming,
     app_tx: mpsc::Sender<AppControlMessage>,
     next_frame_time: Instant,
+    error: Option<Error>,
 }
 
 impl GuiBackend {
-    pub fn new(app_tx: mpsc::Sender<AppControlMessage>) -> Result<Self> {
+    pub fn new(
+        app_tx: mpsc::Sender<AppControlMessage>,
+        main_window: WindowConfig,
+    ) -> Result<Self> {
         let event_loop = EventLoop::<GuiControlMessage>::with_user_event().build()?;
         let event_loop_proxy = event_loop.create_proxy();
         event_loop.set_control_flow(ControlFlow::Wait);
         Ok(Self {
             event_loop: Some(event_loop),
             event_loop_proxy,
-            window: None,
-            renderer: None,
+            windows: HashMap::new(),
+            window_ids: HashMap::new(),
+            main_window,
+            click_timing: ClickTiming::default(),
             app_tx,
             next_frame_time: Instant::now(),
+            error: None,
         })
     }
 
+    /// Timing used to detect double and triple clicks in every window.
+    pub fn with_click_timing(mut self, click_timing: ClickTiming) -> Self {
+        self.click_timing = click_timing;
+        self
+    }
+
     pub fn request_redraw(&self) {
-        if let Some(window) = &self.window {
-            window.as_ref().request_redraw();
+        for window in self.windows.values() {
+            window.window.request_redraw();
+        }
+    }
+
+    fn create_window(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        id: WindowId,
+        config: &WindowConfig,
+    ) -> Result<WindowSize> {
+        if self.window_ids.contains_key(&id) {
+            tracing::info!("Window {:?} already created", id);
+            return Ok(self.window(id)?.size());
+        }
+
+        let window = GuiWindow::new(event_loop, id, config, self.click_timing)?;
+        let winit_id = window.window.id();
+        let size = window.size();
+        self.windows.insert(winit_id, window);
+        self.window_ids.insert(id, winit_id);
+        tracing::info!("Window {:?} created", id);
+
+        self.send_app_message(AppControlMessage::WindowCreated(id));
+        self.send_app_message(AppControlMessage::WindowResized { window: id, size });
+        Ok(size)
+    }
+
+    fn window(&self, id: WindowId) -> Result<&GuiWindow> {
+        self.window_ids
+            .get(&id)
+            .and_then(|winit_id| self.windows.get(winit_id))
+            .ok_or(Error::WindowNotFound(id))
+    }
+
+    fn window_mut(&mut self, id: WindowId) -> Result<&mut GuiWindow> {
+        self.window_ids
+            .get(&id)
+            .and_then(|winit_id| self.windows.get_mut(winit_id))
+            .ok_or(Error::WindowNotFound(id))
+    }
+
+    /// Executes a request and answers it. A dropped receiver only means the caller stopped
+    /// waiting, so send errors are ignored.
+    fn handle_request(&mut self, event_loop: &ActiveEventLoop, request: GuiRequest) {
+        match request {
+            GuiRequest::CreateWindow { id, config, reply } => {
+                let result = self.create_window(event_loop, id, &config);
+                if let Err(err) = &result {
+                    tracing::error!("Failed to create window {:?}: {err}", id);
+                }
+                let _ = reply.send(result);
+            }
+            GuiRequest::SetTitle { id, title, reply } => {
+                let result = self
+                    .window(id)
+                    .map(|window| window.window.set_title(&title));
+                let _ = reply.send(result);
+            }
+            GuiRequest::SetCursor { id, cursor, reply } => {
+                let result = self
+                    .window(id)
+                    .map(|window| window.window.set_cursor(cursor));
+                let _ = reply.send(result);
+            }
+            GuiRequest::RequestRedraw { id, reply } => {
+                let result = self.window(id).map(|window| window.window.request_redraw());
+                let _ = reply.send(result);
+            }
+            GuiRequest::WindowSize { id, reply } => {
+                let _ = reply.send(self.window(id).map(GuiWindow::size));
+            }
         }
     }
 
-    pub async fn create_renderer(&mut self) {
-        if self.renderer.is_some() {
-            tracing::info!("Renderer already created");
+    /// Closing the main or the last window asks the app to exit instead. The window stays
+    /// open until the app confirms with [`GuiControlMessage::Shutdown`], so a listener that
+    /// prevents the exit keeps it.
+    fn close_window(&mut self, event_loop: &ActiveEventLoop, id: WindowId) {
+        if !self.window_ids.contains_key(&id) {
             return;
         }
-        if let Some(window) = &self.window {
-            let window = Arc::clone(window);
-            let renderer = Renderer::new(window).await;
-            self.renderer = Some(renderer);
-            tracing::info!("Renderer created");
+        if id == WindowId::MAIN || self.windows.len() == 1 {
+            self.request_exit(event_loop);
         } else {
-            tracing::info!("Window dont exist");
+            self.remove_window(id);
         }
     }
 
-    // pub fn create_window(&self, title: &str, width: u32, height: u32) -> Window {
-    //     // TODO: create window (primary window already exist)
-    //     todo!()
-    // }
+    fn remove_window(&mut self, id: WindowId) {
+        let Some(winit_id) = self.window_ids.remove(&id) else {
+            return;
+        };
+        self.windows.remove(&winit_id);
+        tracing::info!("Window {:?} closed", id);
+        self.send_app_message(AppControlMessage::WindowClosed(id));
+    }
 
-    pub fn get_proxy(&self) -> EventLoopProxy<GuiControlMessage> {
-        self.event_loop_proxy.clone()
+    /// Closes the remaining windows once the app confirmed the exit and stops the event loop.
+    fn shutdown(&mut self, event_loop: &ActiveEventLoop) {
+        let ids: Vec<_> = self.window_ids.keys().copied().collect();
+        for id in ids {
+            self.remove_window(id);
+        }
+        event_loop.exit();
+    }
+
+    /// Lets the app decide whether to exit; exits right away if the app is gone.
+    fn request_exit(&self, event_loop: &ActiveEventLoop) {
+        if self
+            .app_tx
+            .send(AppControlMessage::RequestShutdown)
+            .is_err()
+        {
+            event_loop.exit();
+        }
+    }
+
+    pub fn get_proxy(&self) -> GuiProxy {
+        GuiProxy::Winit(self.event_loop_proxy.clone())
+    }
+
+    fn send_app_message(&self, message: AppControlMessage) {
+        if self.app_tx.send(message).is_err() {
+            tracing::error!("Send message error");
+        }
     }
 
     pub fn run(mut self) -> Result<()> {
         let event_loop = self.event_loop.take().unwrap();
         event_loop.run_app(&mut self)?;
-        Ok(())
+        match self.error.take() {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    /// Stops the event loop because of an error the GUI can not recover from.
+    /// The error is returned from [`GuiBackend::run`].
+    fn fail(&mut self, event_loop: &ActiveEventLoop, err: Error) {
+        tracing::error!("Unrecoverable GUI error: {err}");
+        self.error = Some(err);
+        event_loop.exit();
     }
 }
 
 impl ApplicationHandler<GuiControlMessage> for GuiBackend {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
-        let window = event_loop
-            .create_window(Window::default_attributes())
-            .unwrap();
-        let window = Arc::new(window);
-        self.window = Some(Arc::clone(&window));
-        let renderer = pollster::block_on(Renderer::new(window));
-        self.renderer = Some(renderer);
+        self.send_app_message(AppControlMessage::Resumed);
+        let config = self.main_window.clone();
+        if let Err(err) = self.create_window(event_loop, WindowId::MAIN, &config) {
+            self.fail(event_loop, err);
+        }
     }
 
     fn window_event(
         &mut self,
         event_loop: &ActiveEventLoop,
-        _window_id: WindowId,
+        window_id: winit::window::WindowId,
         event: WindowEvent,
     ) {
-        // let window = self.window.as_ref().unwrap();
+        let Some(window) = self.windows.get_mut(&window_id) else {
+            return;
+        };
+        let id = window.id;
+
+        let scale_factor = window.window.scale_factor();
+        let app_tx = &self.app_tx;
+        window.input.translate(&event, scale_factor, |event| {
+            if app_tx
+                .send(AppControlMessage::Input { window: id, event })
+                .is_err()
+            {
+                tracing::error!("Send message error");
+            }
+        });
 
         match event {
             WindowEvent::CloseRequested => {
-                event_loop.exit();
+                self.close_window(event_loop, id);
+            }
+            WindowEvent::Resized(size) => {
+                window.renderer.resize(size);
+                window.window.request_redraw();
+                let size = window.size();
+                self.send_app_message(AppControlMessage::WindowResized { window: id, size });
+            }
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                let size = WindowSize::new(window.window.inner_size(), scale_factor);
+                self.send_app_message(AppControlMessage::ScaleFactorChanged { window: id, size });
             }
             WindowEvent::RedrawRequested => {
                 tracing::trace!("RedrawRequested");
-                let renderer = self.renderer.as_mut().unwrap();
-
-                let color = Color {
-                    r: 1.0,
-                    g: 0.0,
-                    b: 1.0,
-                    a: 1.0,
-                };
-                renderer.clear(color);
-            }
-            WindowEvent::KeyboardInput {
-                device_id: _,
-                event,
-                is_synthetic: _,
-            } => {
-                if event.physical_key == PhysicalKey::Code(KeyCode::Escape) {
-                    let send_result = self.app_tx.send(AppControlMessage::RequestShutdown);
-                    if let Err(_) = send_result {
-                        tracing::error!("Send message error");
-                    }
+
+                if let Err(err) = window.renderer.clear(window.background_color) {
+                    self.fail(event_loop, err.into());
                 }
-                tracing::info!("KeyEvent: {:?}", event);
+            }
+            WindowEvent::KeyboardInput { event, .. } => {
+                tracing::trace!("KeyEvent: {:?}", event);
             }
             _ => {
                 // tracing::info!("Window({:?}) event: {:?}", window_id, event);
@@ -133,11 +311,35 @@ impl ApplicationHandler<GuiControlMessage> for GuiBackend {
     fn user_event(&mut self, event_loop: &ActiveEventLoop, event: GuiControlMessage) {
         match event {
             GuiControlMessage::Shutdown => {
-                event_loop.exit();
+                self.shutdown(event_loop);
+            }
+            GuiControlMessage::CloseWindow(id) => {
+                self.close_window(event_loop, id);
+            }
+            GuiControlMessage::UpdateWindow { id, update } => {
+                let mut events = Vec::new();
+                if let Ok(window) = self.window_mut(id) {
+                    match update {
+                        WindowUpdate::PointerCapture(captured) => window
+                            .input
+                            .set_capture(captured, |event| events.push(event)),
+                        update => window.apply(update),
+                    }
+                }
+                for event in events {
+                    self.send_app_message(AppControlMessage::Input { window: id, event });
+                }
+            }
+            GuiControlMessage::Request(request) => {
+                self.handle_request(event_loop, request);
             }
         }
     }
 
+    fn suspended(&mut self, _event_loop: &ActiveEventLoop) {
+        self.send_app_message(AppControlMessage::Suspended);
+    }
+
     fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
         const FRAME_DURATION: Duration = Duration::from_nanos(16_666_667);
 
@@ -1,61 +1,309 @@
-use std::{num::NonZero, sync::Arc};
+use std::{
+    num::NonZero,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
 
-use brul_utils::{Color, Rect};
-use wgpu;
+use crate::{
+    error::{RenderError, Result},
+    image::Image,
+};
+use brul_utils::Color;
 use winit::window::Window;
 
+enum RenderTarget {
+    Surface {
+        _window: Arc<Window>,
+        surface: wgpu::Surface<'static>,
+        format: wgpu::TextureFormat,
+    },
+    Texture {
+        texture: wgpu::Texture,
+        format: wgpu::TextureFormat,
+    },
+}
+
+struct Frame {
+    view: wgpu::TextureView,
+    surface_texture: Option<wgpu::SurfaceTexture>,
+}
+
+/// What [`Renderer::begin_frame`] does after failing to acquire the surface texture.
+#[derive(Debug, PartialEq, Eq)]
+enum SurfaceRecovery {
+    /// Reconfigure the surface and try once more.
+    Reconfigure,
+    SkipFrame,
+}
+
+/// Outdated and lost surfaces are reconfigured once, a timeout skips the frame and running
+/// out of memory is fatal.
+fn surface_recovery(err: &wgpu::SurfaceError, attempt: usize) -> Result<SurfaceRecovery> {
+    match err {
+        wgpu::SurfaceError::Outdated | wgpu::SurfaceError::Lost if attempt == 0 => {
+            Ok(SurfaceRecovery::Reconfigure)
+        }
+        wgpu::SurfaceError::OutOfMemory => Err(RenderError::SurfaceOutOfMemory),
+        _ => Ok(SurfaceRecovery::SkipFrame),
+    }
+}
+
 pub struct Renderer {
-    window: Arc<Window>,
+    instance: wgpu::Instance,
     device: wgpu::Device,
     queue: wgpu::Queue,
+    device_lost: Arc<AtomicBool>,
     size: winit::dpi::PhysicalSize<u32>,
-    surface: wgpu::Surface<'static>,
-    surface_format: wgpu::TextureFormat,
+    target: RenderTarget,
 }
 
 impl Renderer {
-    pub async fn new(window: Arc<Window>) -> Self {
+    pub async fn new(window: Arc<Window>) -> Result<Self> {
         let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
 
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions::default())
-            .await
-            .unwrap();
-
-        let (device, queue) = adapter
-            .request_device(&wgpu::DeviceDescriptor::default())
-            .await
-            .unwrap();
+        let surface = instance.create_surface(Arc::clone(&window))?;
+        let adapter = Self::request_adapter(&instance, Some(&surface)).await?;
+        let (device, queue, device_lost) = Self::request_device(&adapter).await?;
 
         let size = window.inner_size();
 
-        let surface = instance.create_surface(Arc::clone(&window)).unwrap();
         let surface_caps = surface.get_capabilities(&adapter);
-        let surface_format = surface_caps.formats[0];
+        let surface_format = *surface_caps
+            .formats
+            .first()
+            .ok_or(RenderError::SurfaceUnsupported)?;
 
         let renderer = Self {
-            window,
+            instance,
             device,
             queue,
+            device_lost,
             size,
-            surface,
-            surface_format,
+            target: RenderTarget::Surface {
+                _window: window,
+                surface,
+                format: surface_format,
+            },
         };
         renderer.configure_surface();
-        renderer
+        Ok(renderer)
     }
 
-    pub fn _resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+    /// Creates a renderer drawing into an offscreen texture instead of a window surface.
+    ///
+    /// Prefers the software (fallback) adapter so it works on machines without a GPU or display.
+    pub async fn new_offscreen(width: u32, height: u32) -> Result<Self> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+
+        let adapter = Self::request_adapter(&instance, None).await?;
+        let (device, queue, device_lost) = Self::request_device(&adapter).await?;
+
+        let size = winit::dpi::PhysicalSize::new(width.max(1), height.max(1));
+        let format = wgpu::TextureFormat::Rgba8UnormSrgb;
+        let texture = Self::create_texture(&device, size, format);
+
+        Ok(Self {
+            instance,
+            device,
+            queue,
+            device_lost,
+            size,
+            target: RenderTarget::Texture { texture, format },
+        })
+    }
+
+    async fn request_adapter(
+        instance: &wgpu::Instance,
+        surface: Option<&wgpu::Surface<'static>>,
+    ) -> Result<wgpu::Adapter> {
+        if surface.is_some() {
+            let adapter = instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    compatible_surface: surface,
+                    ..Default::default()
+                })
+                .await?;
+            return Ok(adapter);
+        }
+
+        match instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                force_fallback_adapter: true,
+                ..Default::default()
+            })
+            .await
+        {
+            Ok(adapter) => Ok(adapter),
+            Err(err) => {
+                tracing::warn!("Software adapter not available ({err}), trying any adapter");
+                Ok(instance
+                    .request_adapter(&wgpu::RequestAdapterOptions::default())
+                    .await?)
+            }
+        }
+    }
+
+    async fn request_device(
+        adapter: &wgpu::Adapter,
+    ) -> Result<(wgpu::Device, wgpu::Queue, Arc<AtomicBool>)> {
+        let (device, queue) = adapter
+            .request_device(&wgpu::DeviceDescriptor::default())
+            .await?;
+
+        let device_lost = Arc::new(AtomicBool::new(false));
+        let lost_flag = Arc::clone(&device_lost);
+        device.set_device_lost_callback(move |reason, message| {
+            if reason != wgpu::DeviceLostReason::Destroyed {
+                tracing::error!("GPU device lost ({reason:?}): {message}");
+                lost_flag.store(true, Ordering::Release);
+            }
+        });
+
+        Ok((device, queue, device_lost))
+    }
+
+    /// Replaces a lost device with a new one and rebuilds everything that depended on it.
+    fn recover_device(&mut self) -> Result<()> {
+        tracing::warn!("Recreating GPU device");
+        let surface = match &self.target {
+            RenderTarget::Surface { surface, .. } => Some(surface),
+            RenderTarget::Texture { .. } => None,
+        };
+        let lost = |err| RenderError::DeviceLost(Box::new(err));
+        let adapter =
+            pollster::block_on(Self::request_adapter(&self.instance, surface)).map_err(lost)?;
+        let (device, queue, device_lost) =
+            pollster::block_on(Self::request_device(&adapter)).map_err(lost)?;
+
+        self.device = device;
+        self.queue = queue;
+        self.device_lost = device_lost;
+
+        if let RenderTarget::Texture { texture, format } = &mut self.target {
+            *texture = Self::create_texture(&self.device, self.size, *format);
+        }
+        self.configure_surface();
+        Ok(())
+    }
+
+    fn create_texture(
+        device: &wgpu::Device,
+        size: winit::dpi::PhysicalSize<u32>,
+        format: wgpu::TextureFormat,
+    ) -> wgpu::Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Offscreen target"),
+            size: wgpu::Extent3d {
+                width: size.width,
+                height: size.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        })
+    }
+
+    pub fn size(&self) -> winit::dpi::PhysicalSize<u32> {
+        self.size
+    }
+
+    /// `true` when drawing into an offscreen texture that can be read back.
+    pub fn is_offscreen(&self) -> bool {
+        matches!(self.target, RenderTarget::Texture { .. })
+    }
+
+    /// Copies the last rendered frame of an offscreen target back to the CPU.
+    pub fn read_pixels(&self) -> Result<Image> {
+        let RenderTarget::Texture { texture, format } = &self.target else {
+            return Err(RenderError::UnreadableTarget);
+        };
+
+        let width = self.size.width;
+        let height = self.size.height;
+        let bytes_per_pixel = format.block_copy_size(None).unwrap_or(4);
+        let unpadded_bytes_per_row = width * bytes_per_pixel;
+        let padded_bytes_per_row = unpadded_bytes_per_row
+            .div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+            * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Readback buffer"),
+            size: padded_bytes_per_row as u64 * height as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Readback encoder"),
+            });
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            texture.size(),
+        );
+        self.queue.submit(Some(encoder.finish()));
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let slice = buffer.slice(..);
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = tx.send(result);
+        });
+        self.device.poll(wgpu::PollType::wait_indefinitely())?;
+        // The callback is dropped without being called if the device is lost.
+        rx.recv().map_err(|_| RenderError::ReadbackAborted)??;
+
+        let mut pixels = Vec::with_capacity((unpadded_bytes_per_row * height) as usize);
+        {
+            let data = slice.get_mapped_range();
+            for row in data.chunks(padded_bytes_per_row as usize) {
+                pixels.extend_from_slice(&row[..unpadded_bytes_per_row as usize]);
+            }
+        }
+        buffer.unmap();
+
+        Image::new(width, height, pixels)
+    }
+
+    /// Resizes the render target. Zero sizes (minimized windows) are ignored.
+    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        if new_size.width == 0 || new_size.height == 0 || new_size == self.size {
+            return;
+        }
         self.size = new_size;
 
+        if let RenderTarget::Texture { texture, format } = &mut self.target {
+            *texture = Self::create_texture(&self.device, new_size, *format);
+        }
         self.configure_surface();
     }
 
     fn configure_surface(&self) {
+        let RenderTarget::Surface {
+            surface, format, ..
+        } = &self.target
+        else {
+            return;
+        };
+
         let surface_config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: self.surface_format,
-            view_formats: vec![self.surface_format.add_srgb_suffix()],
+            format: *format,
+            view_formats: vec![format.add_srgb_suffix()],
             alpha_mode: wgpu::CompositeAlphaMode::Auto,
             width: self.size.width,
             height: self.size.height,
@@ -63,33 +311,68 @@ impl Renderer {
             present_mode: wgpu::PresentMode::AutoVsync,
         };
 
-        self.surface.configure(&self.device, &surface_config);
+        surface.configure(&self.device, &surface_config);
     }
 
-    pub fn _render() {}
+    /// Acquires the texture for the next frame.
+    ///
+    /// Returns `Ok(None)` when the frame should be skipped (timeout, surface still being
+    /// recreated), and `Err` only for failures the renderer can not recover from.
+    fn begin_frame(&mut self) -> Result<Option<Frame>> {
+        if self.device_lost.load(Ordering::Acquire) {
+            self.recover_device()?;
+        }
+
+        let surface = match &self.target {
+            RenderTarget::Surface { surface, .. } => surface,
+            RenderTarget::Texture { texture, .. } => {
+                return Ok(Some(Frame {
+                    view: texture.create_view(&wgpu::TextureViewDescriptor::default()),
+                    surface_texture: None,
+                }));
+            }
+        };
+
+        for attempt in 0..2 {
+            let err = match surface.get_current_texture() {
+                Ok(output) => {
+                    let view = output
+                        .texture
+                        .create_view(&wgpu::TextureViewDescriptor::default());
+                    return Ok(Some(Frame {
+                        view,
+                        surface_texture: Some(output),
+                    }));
+                }
+                Err(err) => err,
+            };
+            match surface_recovery(&err, attempt)? {
+                SurfaceRecovery::Reconfigure => {
+                    tracing::debug!("Surface outdated or lost, reconfiguring");
+                    self.configure_surface();
+                }
+                SurfaceRecovery::SkipFrame => {
+                    tracing::debug!("Skipping frame: {err}");
+                    return Ok(None);
+                }
+            }
+        }
 
-    fn _begin_frame(&mut self) -> Option<wgpu::TextureView> {
-        let output = self.surface.get_current_texture().ok()?;
-        let view = output
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
-        Some(view)
+        Ok(None)
     }
 
-    fn _end_frame(&mut self) {
-        self.queue.submit(None);
+    fn end_frame(&mut self, frame: Frame, encoder: wgpu::CommandEncoder) {
+        self.queue.submit(Some(encoder.finish()));
+        if let Some(surface_texture) = frame.surface_texture {
+            surface_texture.present();
+        }
     }
 
-    pub fn clear(&mut self, color: Color) {
-        let surface_texture = match self.surface.get_current_texture() {
-            Ok(output) => output,
-            Err(_) => return,
+    pub fn clear(&mut self, color: Color) -> Result<()> {
+        let Some(frame) = self.begin_frame()? else {
+            return Ok(());
         };
 
-        let texture_view = surface_texture
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
-
         let mut encoder = self
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
@@ -107,7 +390,7 @@ impl Renderer {
         };
 
         let color_atachments = [Some(wgpu::RenderPassColorAttachment {
-            view: &texture_view,
+            view: &frame.view,
             resolve_target: None,
             ops: operations,
             depth_slice: None,
@@ -124,7 +407,62 @@ impl Renderer {
 
         drop(render_pass);
 
-        self.queue.submit(Some(encoder.finish()));
-        surface_texture.present();
+        self.end_frame(frame, encoder);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn outdated_and_lost_surfaces_are_reconfigured_once() {
+        for err in [wgpu::SurfaceError::Outdated, wgpu::SurfaceError::Lost] {
+            assert_eq!(
+                surface_recovery(&err, 0).unwrap(),
+                SurfaceRecovery::Reconfigure
+            );
+            assert_eq!(
+                surface_recovery(&err, 1).unwrap(),
+                SurfaceRecovery::SkipFrame
+            );
+        }
+    }
+
+    #[test]
+    fn timeout_skips_the_frame_and_out_of_memory_fails() {
+        assert_eq!(
+            surface_recovery(&wgpu::SurfaceError::Timeout, 0).unwrap(),
+            SurfaceRecovery::SkipFrame
+        );
+        assert_eq!(
+            surface_recovery(&wgpu::SurfaceError::Other, 0).unwrap(),
+            SurfaceRecovery::SkipFrame
+        );
+        assert!(matches!(
+            surface_recovery(&wgpu::SurfaceError::OutOfMemory, 0),
+            Err(RenderError::SurfaceOutOfMemory)
+        ));
+    }
+
+    #[test]
+    fn lost_device_is_recreated_on_the_next_frame() {
+        let mut renderer = match pollster::block_on(Renderer::new_offscreen(4, 4)) {
+            Ok(renderer) => renderer,
+            Err(err) => {
+                eprintln!("skipping lost_device_is_recreated_on_the_next_frame: {err}");
+                return;
+            }
+        };
+        renderer.clear(Color::rgb(1.0, 0.0, 0.0)).unwrap();
+
+        renderer.device_lost.store(true, Ordering::Release);
+        renderer.clear(Color::rgb(0.0, 0.0, 1.0)).unwrap();
+
+        assert!(!renderer.device_lost.load(Ordering::Acquire));
+        // The target texture was rebuilt on the new device and can be read back.
+        let image = renderer.read_pixels().unwrap();
+        assert_eq!(image.pixel(0, 0), [0, 0, 255, 255]);
     }
 }
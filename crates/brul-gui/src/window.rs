@@ -0,0 +1,137 @@
+use crate::{input::InputState, renderer::Renderer};
+use brul_utils::{
+    Color, Fullscreen, Result, Size, WindowConfig, WindowId, WindowSize, WindowUpdate,
+    input::ClickTiming,
+};
+use std::sync::Arc;
+use winit::{
+    dpi::{LogicalPosition, LogicalSize},
+    event_loop::ActiveEventLoop,
+    monitor::MonitorHandle,
+    window::{Window, WindowAttributes, WindowLevel},
+};
+
+/// A native window together with the renderer drawing into it.
+pub(crate) struct GuiWindow {
+    pub(crate) id: WindowId,
+    pub(crate) window: Arc<Window>,
+    pub(crate) renderer: Renderer,
+    pub(crate) background_color: Color,
+    pub(crate) input: InputState,
+}
+
+impl GuiWindow {
+    pub(crate) fn new(
+        event_loop: &ActiveEventLoop,
+        id: WindowId,
+        config: &WindowConfig,
+        click_timing: ClickTiming,
+    ) -> Result<Self> {
+        let attributes = window_attributes(config, event_loop.primary_monitor());
+        let window = Arc::new(event_loop.create_window(attributes)?);
+        window.set_ime_allowed(config.ime_allowed);
+        let renderer = pollster::block_on(Renderer::new(Arc::clone(&window)))?;
+
+        Ok(Self {
+            id,
+            window,
+            renderer,
+            background_color: config.background_color,
+            input: InputState::new(click_timing),
+        })
+    }
+
+    pub(crate) fn size(&self) -> WindowSize {
+        WindowSize::new(self.window.inner_size(), self.window.scale_factor())
+    }
+
+    pub(crate) fn apply(&mut self, update: WindowUpdate) {
+        let window = &self.window;
+        match update {
+            WindowUpdate::Title(title) => window.set_title(&title),
+            WindowUpdate::InnerSize(size) => {
+                // The actual size arrives later as a `Resized` event.
+                let _ = window.request_inner_size(logical_size(size));
+            }
+            WindowUpdate::MinInnerSize(size) => window.set_min_inner_size(size.map(logical_size)),
+            WindowUpdate::MaxInnerSize(size) => window.set_max_inner_size(size.map(logical_size)),
+            WindowUpdate::Position(position) => {
+                window.set_outer_position(LogicalPosition::new(position.x, position.y))
+            }
+            WindowUpdate::Decorations(decorations) => window.set_decorations(decorations),
+            WindowUpdate::Resizable(resizable) => window.set_resizable(resizable),
+            WindowUpdate::AlwaysOnTop(always_on_top) => {
+                window.set_window_level(window_level(always_on_top))
+            }
+            WindowUpdate::Transparent(transparent) => window.set_transparent(transparent),
+            WindowUpdate::Fullscreen(mode) => {
+                window.set_fullscreen(fullscreen(mode, window.current_monitor()))
+            }
+            WindowUpdate::BackgroundColor(color) => {
+                self.background_color = color;
+                window.request_redraw();
+            }
+            WindowUpdate::ImeAllowed(allowed) => window.set_ime_allowed(allowed),
+            WindowUpdate::ImeCursorArea { position, size } => window.set_ime_cursor_area(
+                LogicalPosition::new(position.x, position.y),
+                logical_size(size),
+            ),
+            WindowUpdate::CursorVisible(visible) => window.set_cursor_visible(visible),
+            // Capture lives in the input state, the backend handles it.
+            WindowUpdate::PointerCapture(_) => {}
+        }
+    }
+}
+
+fn window_attributes(config: &WindowConfig, monitor: Option<MonitorHandle>) -> WindowAttributes {
+    let mut attributes = Window::default_attributes()
+        .with_title(config.title.clone())
+        .with_inner_size(logical_size(config.inner_size))
+        .with_decorations(config.decorations)
+        .with_resizable(config.resizable)
+        .with_window_level(window_level(config.always_on_top))
+        .with_transparent(config.transparent)
+        .with_fullscreen(fullscreen(config.fullscreen, monitor));
+
+    if let Some(size) = config.min_inner_size {
+        attributes = attributes.with_min_inner_size(logical_size(size));
+    }
+    if let Some(size) = config.max_inner_size {
+        attributes = attributes.with_max_inner_size(logical_size(size));
+    }
+    if let Some(position) = config.position {
+        attributes = attributes.with_position(LogicalPosition::new(position.x, position.y));
+    }
+    attributes
+}
+
+fn logical_size(size: Size) -> LogicalSize<f32> {
+    LogicalSize::new(size.width, size.height)
+}
+
+fn window_level(always_on_top: bool) -> WindowLevel {
+    if always_on_top {
+        WindowLevel::AlwaysOnTop
+    } else {
+        WindowLevel::Normal
+    }
+}
+
+fn fullscreen(
+    mode: Option<Fullscreen>,
+    monitor: Option<MonitorHandle>,
+) -> Option<winit::window::Fullscreen> {
+    match mode? {
+        Fullscreen::Borderless => Some(winit::window::Fullscreen::Borderless(monitor)),
+        Fullscreen::Exclusive => {
+            let video_mode = monitor?.video_modes().next();
+            match video_mode {
+                Some(video_mode) => Some(winit::window::Fullscreen::Exclusive(video_mode)),
+                None => {
+                    tracing::warn!("No video mode for exclusive fullscreen, using borderless");
+                    Some(winit::window::Fullscreen::Borderless(None))
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use brul_gui::{
+    RenderError,
+    image::{Image, UPDATE_GOLDEN_ENV},
+    renderer::Renderer,
+};
+use brul_utils::Color;
+
+fn golden_path(name: &str) -> String {
+    format!("{}/tests/golden/{name}", env!("CARGO_MANIFEST_DIR"))
+}
+
+#[test]
+fn clear_matches_golden() {
+    let mut renderer = match pollster::block_on(Renderer::new_offscreen(64, 48)) {
+        Ok(renderer) => renderer,
+        Err(err) => {
+            eprintln!("skipping clear_matches_golden, no wgpu adapter: {err}");
+            return;
+        }
+    };
+
+    renderer.clear(Color::rgb(1.0, 0.0, 1.0)).unwrap();
+    let image = renderer.read_pixels().unwrap();
+
+    assert_eq!((image.width(), image.height()), (64, 48));
+    let diff = image
+        .compare_golden(golden_path("clear_magenta.png"), 2)
+        .unwrap();
+    assert!(diff.is_match(), "frame differs from golden: {diff:?}");
+}
+
+#[test]
+fn diff_respects_tolerance() {
+    let a = Image::new(2, 1, vec![10, 20, 30, 255, 0, 0, 0, 255]).unwrap();
+    let b = Image::new(2, 1, vec![12, 20, 30, 255, 0, 0, 9, 255]).unwrap();
+
+    let diff = a.diff(&b, 2);
+    assert_eq!(diff.differing_pixels, 1);
+    assert_eq!(diff.max_difference, 9);
+    assert!(!diff.is_match());
+    assert!(a.diff(&b, 9).is_match());
+    assert!(!a.diff(&Image::new(1, 1, vec![0; 4]).unwrap(), 255).is_match());
+}
+
+#[test]
+fn image_rejects_mismatched_buffers() {
+    let result = Image::new(2, 2, vec![0; 12]);
+
+    assert!(matches!(
+        result,
+        Err(RenderError::InvalidImageSize {
+            width: 2,
+            height: 2,
+            len: 12
+        })
+    ));
+}
+
+#[test]
+fn missing_golden_is_an_error() {
+    // Updating goldens writes the missing file instead.
+    if std::env::var_os(UPDATE_GOLDEN_ENV).is_some() {
+        return;
+    }
+
+    let image = Image::new(1, 1, vec![0; 4]).unwrap();
+    let result = image.compare_golden(golden_path("does_not_exist.png"), 0);
+
+    assert!(matches!(result, Err(RenderError::MissingGolden(_))));
+}
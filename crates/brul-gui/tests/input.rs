@@ -0,0 +1,232 @@
+use brul_gui::{HeadlessBackend, SyntheticEvent};
+use brul_utils::{
+    AppControlMessage, GuiControlMessage, Point, Size, WindowConfig, WindowId, WindowUpdate,
+    input::{ImeEvent, InputEvent, Key, KeyCode, Modifiers, PointerButton, PointerButtons},
+};
+use std::sync::mpsc;
+use winit::event::MouseButton;
+
+fn inputs(rx: &mpsc::Receiver<AppControlMessage>) -> Vec<InputEvent> {
+    rx.try_iter()
+        .filter_map(|message| match message {
+            AppControlMessage::Input { window, event } => {
+                assert_eq!(window, WindowId::MAIN);
+                Some(event)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn synthetic_events_are_forwarded_as_input() {
+    let (tx, rx) = mpsc::channel();
+    let mut backend = HeadlessBackend::new(tx, WindowConfig::default()).unwrap();
+    backend.step();
+    assert!(inputs(&rx).is_empty());
+
+    let shift = Modifiers {
+        shift: true,
+        ..Default::default()
+    };
+    for event in [
+        SyntheticEvent::ModifiersChanged(shift),
+        SyntheticEvent::CursorMoved { x: 20.0, y: 10.0 },
+        SyntheticEvent::MouseInput {
+            button: MouseButton::Left,
+            pressed: true,
+        },
+        SyntheticEvent::KeyPressed(KeyCode::KeyA),
+        SyntheticEvent::Text("A".into()),
+    ] {
+        backend.inject(WindowId::MAIN, event);
+    }
+    backend.step();
+
+    let events = inputs(&rx);
+    assert_eq!(events.len(), 5);
+    assert_eq!(events[0], InputEvent::ModifiersChanged(shift));
+    let InputEvent::PointerDown {
+        button, pointer, ..
+    } = &events[2]
+    else {
+        panic!("expected a pointer down, got {:?}", events[2]);
+    };
+    assert_eq!(*button, PointerButton::Primary);
+    assert_eq!(pointer.position, Point::new(20.0, 10.0));
+    assert!(pointer.buttons.contains(PointerButton::Primary));
+    assert_eq!(pointer.modifiers, shift);
+    let InputEvent::KeyDown(key) = &events[3] else {
+        panic!("expected a key down, got {:?}", events[3]);
+    };
+    assert_eq!(key.key, Key::Character("a".into()));
+    assert_eq!(key.physical_key, Some(KeyCode::KeyA));
+    assert_eq!(events[4], InputEvent::Text("A".into()));
+}
+
+#[test]
+fn ime_composition_is_forwarded_once_allowed() {
+    let (tx, rx) = mpsc::channel();
+    let mut backend = HeadlessBackend::new(tx, WindowConfig::default()).unwrap();
+    backend.step();
+    let _ = inputs(&rx);
+
+    backend.inject_composition(WindowId::MAIN, &["に"], "日");
+    backend.step();
+    assert!(
+        inputs(&rx).is_empty(),
+        "IME input must be dropped while not allowed"
+    );
+
+    let proxy = backend.get_proxy();
+    for update in [
+        WindowUpdate::ImeAllowed(true),
+        WindowUpdate::ImeCursorArea {
+            position: Point::new(10.0, 20.0),
+            size: Size::new(1.0, 16.0),
+        },
+    ] {
+        proxy
+            .send_event(GuiControlMessage::UpdateWindow {
+                id: WindowId::MAIN,
+                update,
+            })
+            .unwrap();
+    }
+    backend.inject_composition(WindowId::MAIN, &["に", "にほ"], "日本");
+    backend.step();
+
+    assert_eq!(
+        backend.ime_cursor_area(WindowId::MAIN),
+        Some((Point::new(10.0, 20.0), Size::new(1.0, 16.0)))
+    );
+    let preedit = |text: &str, cursor| {
+        InputEvent::Ime(ImeEvent::Preedit {
+            text: text.into(),
+            cursor,
+        })
+    };
+    assert_eq!(
+        inputs(&rx),
+        [
+            InputEvent::Ime(ImeEvent::Enabled),
+            preedit("に", Some((3, 3))),
+            preedit("にほ", Some((6, 6))),
+            preedit("", None),
+            InputEvent::Ime(ImeEvent::Commit("日本".into())),
+            InputEvent::Text("日本".into()),
+        ]
+    );
+}
+
+#[test]
+fn pointer_tracks_clicks_capture_and_hover() {
+    let (tx, rx) = mpsc::channel();
+    let mut backend = HeadlessBackend::new(tx, WindowConfig::default()).unwrap();
+    backend.step();
+    let _ = inputs(&rx);
+
+    let click = |backend: &mut HeadlessBackend, pressed| {
+        backend.inject(
+            WindowId::MAIN,
+            SyntheticEvent::MouseInput {
+                button: MouseButton::Left,
+                pressed,
+            },
+        )
+    };
+    backend.inject(WindowId::MAIN, SyntheticEvent::CursorEntered);
+    backend.inject(
+        WindowId::MAIN,
+        SyntheticEvent::CursorMoved { x: 10.0, y: 10.0 },
+    );
+    for _ in 0..3 {
+        click(&mut backend, true);
+        click(&mut backend, false);
+    }
+    backend.inject(
+        WindowId::MAIN,
+        SyntheticEvent::CursorMoved { x: 50.0, y: 10.0 },
+    );
+    click(&mut backend, true);
+    backend.step();
+
+    let events = inputs(&rx);
+    assert_eq!(events[0], InputEvent::PointerEntered);
+    let counts: Vec<u32> = events
+        .iter()
+        .filter_map(|event| match event {
+            InputEvent::PointerDown { count, .. } => Some(*count),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(counts, [1, 2, 3, 1]);
+
+    // The left button is still down, so the window keeps the pointer after it leaves.
+    let proxy = backend.get_proxy();
+    for update in [
+        WindowUpdate::PointerCapture(true),
+        WindowUpdate::CursorVisible(false),
+    ] {
+        proxy
+            .send_event(GuiControlMessage::UpdateWindow {
+                id: WindowId::MAIN,
+                update,
+            })
+            .unwrap();
+    }
+    backend.inject(WindowId::MAIN, SyntheticEvent::CursorLeft);
+    backend.inject(
+        WindowId::MAIN,
+        SyntheticEvent::CursorMoved { x: -4.0, y: 10.0 },
+    );
+    backend.step();
+
+    assert_eq!(backend.cursor_visible(WindowId::MAIN), Some(false));
+    let events = inputs(&rx);
+    assert_eq!(events[0], InputEvent::PointerCaptureChanged(true));
+    assert!(matches!(events[1], InputEvent::PointerMoved(_)));
+    assert_eq!(events.len(), 2);
+
+    click(&mut backend, false);
+    backend.step();
+
+    let events = inputs(&rx);
+    assert!(matches!(events[0], InputEvent::PointerUp { .. }));
+    assert_eq!(
+        events[1..],
+        [
+            InputEvent::PointerCaptureChanged(false),
+            InputEvent::PointerLeft,
+        ]
+    );
+}
+
+#[test]
+fn pointer_buttons_track_other_buttons_by_id() {
+    let mut buttons = PointerButtons::default();
+    buttons.insert(PointerButton::Other(30));
+    buttons.insert(PointerButton::Primary);
+
+    assert!(buttons.contains(PointerButton::Other(30)));
+    assert!(!buttons.contains(PointerButton::Other(31)));
+    assert!(!buttons.contains(PointerButton::Other(300)));
+
+    buttons.insert(PointerButton::Other(300));
+    buttons.remove(PointerButton::Other(30));
+    assert!(!buttons.contains(PointerButton::Other(30)));
+    assert!(buttons.contains(PointerButton::Other(300)));
+
+    buttons.remove(PointerButton::Other(300));
+    buttons.remove(PointerButton::Primary);
+    assert!(buttons.is_empty());
+    assert_eq!(buttons, PointerButtons::default());
+}
+
+#[test]
+fn dead_keys_keep_their_character() {
+    use winit::keyboard::Key as WinitKey;
+
+    assert_eq!(Key::from(&WinitKey::Dead(Some('^'))), Key::Dead(Some('^')));
+    assert_eq!(Key::from(&WinitKey::Dead(None)), Key::Dead(None));
+}
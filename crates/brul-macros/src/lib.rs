@@ -1,23 +1,157 @@
 use proc_macro::TokenStream;
-// use quote::quote;
-// use syn::{Attribute, Item};
+use proc_macro2::Span;
+use quote::{format_ident, quote};
+use syn::{
+    FnArg, Ident, ItemFn, LitInt, Pat, Path, Token, parse_macro_input, punctuated::Punctuated,
+};
 
+/// Turns a function into a command that can be invoked by name.
+///
+/// Every argument is extracted from the invocation through `brul::command::CommandArg`:
+/// `State<T>`, `AppHandle` and `Cancellation` come from the app, anything else is
+/// deserialized from the payload field with the argument's name. The function may be
+/// `async` and may return any serializable value or a `Result` whose error implements
+/// `Display`.
+///
+/// `#[brul::command(timeout_ms = 5000)]` cancels the command when it runs for too long.
 #[proc_macro_attribute]
-pub fn command(_attr: TokenStream, item: TokenStream) -> TokenStream {
-    // let item = syn::parse_macro_input!(item as Item);
+pub fn command(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut timeout_ms: Option<LitInt> = None;
+    let parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("timeout_ms") {
+            timeout_ms = Some(meta.value()?.parse()?);
+            Ok(())
+        } else {
+            Err(meta.error("unsupported command option, expected `timeout_ms`"))
+        }
+    });
+    parse_macro_input!(attr with parser);
+    let function = parse_macro_input!(item as ItemFn);
 
-    // let item = match item {
-    //     Item::Fn(item) => item,
-    //     _ => panic!("Only functions are supported"),
-    // };
+    match command_wrapper(&function, timeout_ms) {
+        Ok(wrapper) => quote!(#function #wrapper).into(),
+        Err(err) => {
+            let err = err.to_compile_error();
+            quote!(#function #err).into()
+        }
+    }
+}
+
+/// Collects `#[brul::command]` functions into a `brul::command::Handlers` table.
+///
+/// `generate_handlers![load, settings::save]` registers the commands as `"load"` and
+/// `"save"`.
+#[proc_macro]
+pub fn generate_handlers(input: TokenStream) -> TokenStream {
+    let paths = parse_macro_input!(input with Punctuated::<Path, Token![,]>::parse_terminated);
+
+    let mut entries = Vec::new();
+    for path in paths {
+        let Some(last) = path.segments.last() else {
+            continue;
+        };
+        let name = last.ident.to_string();
+        let mut wrapper = path.clone();
+        if let Some(last) = wrapper.segments.last_mut() {
+            last.ident = wrapper_ident(&last.ident);
+        }
+        entries.push(quote!(.with(#name, #wrapper)));
+    }
+
+    quote!(::brul::command::Handlers::new() #(#entries)*).into()
+}
+
+fn wrapper_ident(ident: &Ident) -> Ident {
+    format_ident!("__brul_command_{}", ident)
+}
+
+fn command_wrapper(
+    function: &ItemFn,
+    timeout_ms: Option<LitInt>,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let sig = &function.sig;
+    if !sig.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &sig.generics,
+            "commands can not be generic",
+        ));
+    }
+
+    let mut args = Vec::new();
+    let mut types = Vec::new();
+    let mut extract = Vec::new();
+    for (index, input) in sig.inputs.iter().enumerate() {
+        let FnArg::Typed(arg) = input else {
+            return Err(syn::Error::new_spanned(
+                input,
+                "commands can not take `self`",
+            ));
+        };
+        let name = match &*arg.pat {
+            Pat::Ident(pat) => pat.ident.to_string(),
+            _ => format!("arg{index}"),
+        };
+        let var = Ident::new(&format!("__arg{index}"), Span::call_site());
+        let ty = &arg.ty;
+        extract.push(quote! {
+            let #var = <#ty as ::brul::command::CommandArg>::from_invoke(#name, invoke)?;
+        });
+        args.push(var);
+        types.push(ty);
+    }
 
-    // let name = item.sig.ident.to_string();
+    let ident = &sig.ident;
+    let call = if sig.asyncness.is_some() {
+        quote!(#ident(#(#args),*).await)
+    } else {
+        quote!(#ident(#(#args),*))
+    };
+    // The response is picked by the type of the value, see `brul::command::response`.
+    let response = quote! {{
+        use ::brul::command::response::{ResultKind as _, ValueKind as _};
+        let value = #call;
+        (&value).response_kind().response(value)
+    }};
 
-    // println!("Attr: {:?}", attr);
-    // println!("Item: {:?}", item);
+    // Arguments are extracted before the future is created, so the future does not borrow
+    // the invocation and can be moved to the runtime. The function itself only runs once the
+    // future is polled; a synchronous one runs on the blocking pool.
+    let run = if sig.asyncness.is_some() {
+        quote! {
+            ::std::boxed::Box::pin(async move {
+                let (#(#args,)*) = args?;
+                #response
+            })
+        }
+    } else {
+        quote! {
+            match args {
+                Ok((#(#args,)*)) => ::brul::command::run_blocking(move || #response),
+                Err(err) => ::std::boxed::Box::pin(::std::future::ready(Err(err))),
+            }
+        }
+    };
+    let body = quote! {
+        let args = (|| -> ::std::result::Result<(#(#types,)*), ::brul::command::CommandError> {
+            #(#extract)*
+            Ok((#(#args,)*))
+        })();
+        #run
+    };
 
-    // TODO: implement configure macros function depends on argumens
-    // now return unchanged
+    let timeout =
+        timeout_ms.map(|ms| quote!(.with_timeout(::std::time::Duration::from_millis(#ms))));
 
-    item
+    let vis = &function.vis;
+    let wrapper = wrapper_ident(ident);
+    Ok(quote! {
+        #[doc(hidden)]
+        #[allow(non_upper_case_globals)]
+        #vis const #wrapper: ::brul::command::Command = {
+            fn handler(invoke: &::brul::command::Invoke) -> ::brul::command::CommandFuture {
+                #body
+            }
+            ::brul::command::Command::new(handler) #timeout
+        };
+    })
 }
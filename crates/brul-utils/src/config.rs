@@ -1,7 +1,49 @@
-use std::path::PathBuf;
+use crate::{WindowConfig, input::ClickTiming};
+
+/// Which GUI backend the app runs on.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GuiMode {
+    /// Native windows driven by the winit event loop.
+    #[default]
+    Windowed,
+    /// No display server needed: windows render into offscreen textures.
+    Headless,
+}
 
 #[derive(Default)]
 pub struct Config {
-    app_path: PathBuf,
+    gui_mode: GuiMode,
+    window: WindowConfig,
+    click_timing: ClickTiming,
     // TODO: Add other config options
 }
+
+impl Config {
+    pub fn with_gui_mode(mut self, gui_mode: GuiMode) -> Self {
+        self.gui_mode = gui_mode;
+        self
+    }
+
+    pub fn gui_mode(&self) -> GuiMode {
+        self.gui_mode
+    }
+
+    /// Config of the main window created when the GUI starts.
+    pub fn with_window(mut self, window: WindowConfig) -> Self {
+        self.window = window;
+        self
+    }
+
+    pub fn window(&self) -> &WindowConfig {
+        &self.window
+    }
+
+    pub fn with_click_timing(mut self, click_timing: ClickTiming) -> Self {
+        self.click_timing = click_timing;
+        self
+    }
+
+    pub fn click_timing(&self) -> ClickTiming {
+        self.click_timing
+    }
+}
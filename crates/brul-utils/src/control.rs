@@ -1,43 +1,130 @@
-use winit::event_loop::EventLoopProxy;
+use std::sync::{RwLock, mpsc};
 
-use crate::{Error, Result};
+use futures_channel::oneshot;
+use winit::{
+    event_loop::{EventLoopClosed, EventLoopProxy},
+    window::CursorIcon,
+};
+
+use crate::{Error, Result, WindowConfig, WindowId, WindowSize, WindowUpdate, input::InputEvent};
 
 #[derive(Debug)]
 pub enum AppControlMessage {
     AppStarted,
+    /// Asks the app to exit; the app answers with [`GuiControlMessage::Shutdown`] unless a
+    /// listener prevents it.
     RequestShutdown,
+    Resumed,
+    Suspended,
+    WindowCreated(WindowId),
+    WindowClosed(WindowId),
+    WindowResized {
+        window: WindowId,
+        size: WindowSize,
+    },
+    ScaleFactorChanged {
+        window: WindowId,
+        size: WindowSize,
+    },
+    Input {
+        window: WindowId,
+        event: InputEvent,
+    },
 }
 
 #[derive(Debug, Default)]
 pub enum GuiControlMessage {
+    /// Confirms the exit: the GUI closes its windows and stops.
     #[default]
     Shutdown,
+    CloseWindow(WindowId),
+    UpdateWindow {
+        id: WindowId,
+        update: WindowUpdate,
+    },
+    Request(GuiRequest),
+}
+
+/// Sending half of the channel a [`GuiRequest`] is answered on.
+pub type Reply<T> = oneshot::Sender<Result<T>>;
+
+/// Command the GUI thread answers through its reply channel.
+#[derive(Debug)]
+pub enum GuiRequest {
+    CreateWindow {
+        id: WindowId,
+        config: WindowConfig,
+        reply: Reply<WindowSize>,
+    },
+    SetTitle {
+        id: WindowId,
+        title: String,
+        reply: Reply<()>,
+    },
+    SetCursor {
+        id: WindowId,
+        cursor: CursorIcon,
+        reply: Reply<()>,
+    },
+    RequestRedraw {
+        id: WindowId,
+        reply: Reply<()>,
+    },
+    WindowSize {
+        id: WindowId,
+        reply: Reply<WindowSize>,
+    },
+}
+
+/// Sending side of the GUI thread's message queue, independent of the backend.
+#[derive(Debug, Clone)]
+pub enum GuiProxy {
+    Winit(EventLoopProxy<GuiControlMessage>),
+    Headless(mpsc::Sender<GuiControlMessage>),
+}
+
+impl GuiProxy {
+    pub fn send_event(&self, msg: GuiControlMessage) -> Result<()> {
+        match self {
+            GuiProxy::Winit(proxy) => proxy.send_event(msg)?,
+            GuiProxy::Headless(tx) => tx.send(msg).map_err(|err| EventLoopClosed(err.0))?,
+        }
+        Ok(())
+    }
 }
 
+/// Shared slot for the GUI proxy, filled once the backend is created.
 #[derive(Default, Debug)]
 pub struct EVProxy {
-    proxy: Option<EventLoopProxy<GuiControlMessage>>,
+    proxy: RwLock<Option<GuiProxy>>,
 }
 
 impl EVProxy {
     pub fn new() -> Self {
-        Self { proxy: None }
+        Self {
+            proxy: RwLock::new(None),
+        }
     }
 
-    pub fn set_proxy(&mut self, proxy: EventLoopProxy<GuiControlMessage>) {
-        self.proxy = Some(proxy);
+    pub fn set_proxy(&self, proxy: GuiProxy) {
+        *self.proxy.write().unwrap() = Some(proxy);
     }
 
-    pub fn get_proxy(&self) -> Option<EventLoopProxy<GuiControlMessage>> {
-        self.proxy.clone()
+    pub fn get_proxy(&self) -> Option<GuiProxy> {
+        self.proxy.read().unwrap().clone()
     }
 
     pub fn send(&self, msg: GuiControlMessage) -> Result<()> {
         let proxy = self
-            .proxy
-            .as_ref()
+            .get_proxy()
             .ok_or(Error::WinitOtherError("Proxy not set"))?;
-        let result = proxy.send_event(msg)?;
-        Ok(result)
+        proxy.send_event(msg)
+    }
+
+    /// Sends the request built by `request` and waits for the GUI thread's answer.
+    pub async fn request<T>(&self, request: impl FnOnce(Reply<T>) -> GuiRequest) -> Result<T> {
+        let (reply, rx) = oneshot::channel();
+        self.send(GuiControlMessage::Request(request(reply)))?;
+        rx.await.map_err(|_| Error::GuiNoReply)?
     }
 }
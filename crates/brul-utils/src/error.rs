@@ -1,6 +1,8 @@
+use std::path::PathBuf;
+
 use thiserror::Error;
 
-use crate::GuiControlMessage;
+use crate::{GuiControlMessage, WindowId};
 
 #[derive(Error, Debug)]
 pub enum Error {
@@ -13,8 +15,85 @@ pub enum Error {
     #[error(transparent)]
     WinitEventLoopClosed(#[from] winit::event_loop::EventLoopClosed<GuiControlMessage>),
 
+    #[error(transparent)]
+    WinitOs(#[from] winit::error::OsError),
+
     #[error("{0}")]
     WinitOtherError(&'static str),
+
+    /// No GPU adapter was found, or the adapter could not create a device.
+    #[error("no usable GPU adapter: {0}")]
+    AdapterUnavailable(String),
+
+    /// The GPU device was lost and could not be recreated.
+    #[error("GPU device lost: {0}")]
+    DeviceLost(String),
+
+    /// The window surface could not be created or configured.
+    #[error("window surface can not be recovered: {0}")]
+    SurfaceUnrecoverable(String),
+
+    #[error("out of GPU memory")]
+    GpuOutOfMemory,
+
+    /// The rendered frame could not be copied back to the CPU.
+    #[error("failed to read back the frame: {0}")]
+    Readback(String),
+
+    #[error("failed to encode image: {0}")]
+    ImageEncode(String),
+
+    #[error("failed to decode image: {0}")]
+    ImageDecode(String),
+
+    #[error("golden image {} does not exist, set BRUL_UPDATE_GOLDEN to create it", .0.display())]
+    MissingGolden(PathBuf),
+
+    #[error("window {0:?} does not exist")]
+    WindowNotFound(WindowId),
+
+    #[error("GUI thread dropped the request without replying")]
+    GuiNoReply,
+
+    #[error("app has already exited")]
+    AppNotRunning,
+
+    #[error("invalid shortcut `{0}`")]
+    InvalidShortcut(String),
+
+    #[error("shortcut `{shortcut}` conflicts with `{existing}`")]
+    ShortcutConflict { shortcut: String, existing: String },
+
+    #[error("state of type {0} is already managed")]
+    StateAlreadyManaged(&'static str),
+
+    #[error("command `{0}` is not registered")]
+    UnknownCommand(String),
+
+    #[error("command `{command}` got an invalid argument `{arg}`: {message}")]
+    InvalidCommandArgument {
+        command: String,
+        arg: &'static str,
+        message: String,
+    },
+
+    #[error("command `{command}` failed: {message}")]
+    CommandFailed { command: String, message: String },
+
+    #[error("command `{0}` was cancelled")]
+    CommandCancelled(String),
+
+    #[error("command `{command}` timed out after {timeout:?}")]
+    CommandTimedOut {
+        command: String,
+        timeout: std::time::Duration,
+    },
+
+    #[error("failed to build the tokio runtime: {0}")]
+    RuntimeBuild(#[source] std::io::Error),
+
+    #[error("invalid runtime config: {0}")]
+    InvalidRuntimeConfig(&'static str),
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,359 @@
+//! Input events shared by all GUI backends.
+//!
+//! Positions are logical pixels relative to the window's top left corner.
+
+use crate::{Point, WindowId};
+use std::{path::PathBuf, time::Duration};
+
+pub use winit::keyboard::{KeyCode, NamedKey};
+
+/// Input that happened in `window`. Emitted on the app's event bus, targeted at the window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Input {
+    pub window: WindowId,
+    pub event: InputEvent,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InputEvent {
+    PointerMoved(PointerEvent),
+    PointerDown {
+        button: PointerButton,
+        pointer: PointerEvent,
+        /// Consecutive clicks of `button` at about this position, 2 for a double click, 3
+        /// for a triple click and so on. See [`ClickTiming`].
+        count: u32,
+    },
+    PointerUp {
+        button: PointerButton,
+        pointer: PointerEvent,
+    },
+    PointerEntered,
+    /// The pointer left the window. Delayed until the capture ends while the pointer is
+    /// captured.
+    PointerLeft,
+    /// The window started or stopped receiving pointer events from outside its bounds.
+    PointerCaptureChanged(bool),
+    Scroll {
+        delta: ScrollDelta,
+        pointer: PointerEvent,
+    },
+    KeyDown(KeyEvent),
+    KeyUp(KeyEvent),
+    /// Text typed by the user, after the keyboard layout was applied. Text committed by an
+    /// input method is reported here as well.
+    Text(String),
+    /// Input method state and composition.
+    Ime(ImeEvent),
+    ModifiersChanged(Modifiers),
+    Focused(bool),
+    FileHovered(PathBuf),
+    FileHoverCancelled,
+    FileDropped(PathBuf),
+}
+
+/// Pointer state at the time of a pointer event.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PointerEvent {
+    pub position: Point,
+    /// Buttons held down after the event.
+    pub buttons: PointerButtons,
+    pub modifiers: Modifiers,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PointerButton {
+    Primary,
+    Secondary,
+    Middle,
+    Back,
+    Forward,
+    Other(u16),
+}
+
+impl PointerButton {
+    /// Bit of a named button in [`PointerButtons`]; `Other` buttons are kept by id.
+    fn bit(self) -> Option<u8> {
+        match self {
+            PointerButton::Primary => Some(1),
+            PointerButton::Secondary => Some(1 << 1),
+            PointerButton::Middle => Some(1 << 2),
+            PointerButton::Back => Some(1 << 3),
+            PointerButton::Forward => Some(1 << 4),
+            PointerButton::Other(_) => None,
+        }
+    }
+}
+
+impl From<winit::event::MouseButton> for PointerButton {
+    fn from(button: winit::event::MouseButton) -> Self {
+        use winit::event::MouseButton;
+        match button {
+            MouseButton::Left => PointerButton::Primary,
+            MouseButton::Right => PointerButton::Secondary,
+            MouseButton::Middle => PointerButton::Middle,
+            MouseButton::Back => PointerButton::Back,
+            MouseButton::Forward => PointerButton::Forward,
+            MouseButton::Other(n) => PointerButton::Other(n),
+        }
+    }
+}
+
+/// Last known pointer state of a window.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct PointerState {
+    pub position: Point,
+    pub buttons: PointerButtons,
+    /// Whether the pointer is over the window, as reported by enter and leave events.
+    pub hovered: bool,
+    pub captured: bool,
+}
+
+impl PointerState {
+    /// Updates the state from an input event of the window.
+    pub fn update(&mut self, event: &InputEvent) {
+        match event {
+            InputEvent::PointerMoved(pointer)
+            | InputEvent::PointerDown { pointer, .. }
+            | InputEvent::PointerUp { pointer, .. }
+            | InputEvent::Scroll { pointer, .. } => {
+                self.position = pointer.position;
+                self.buttons = pointer.buttons;
+            }
+            InputEvent::PointerEntered => self.hovered = true,
+            InputEvent::PointerLeft => self.hovered = false,
+            InputEvent::PointerCaptureChanged(captured) => self.captured = *captured,
+            InputEvent::Focused(false) => self.buttons = PointerButtons::default(),
+            _ => {}
+        }
+    }
+}
+
+/// How close in time and space presses must be to count as a double or triple click.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClickTiming {
+    /// Longest delay between two presses.
+    pub interval: Duration,
+    /// Largest distance between two presses, in logical pixels.
+    pub distance: f32,
+}
+
+impl Default for ClickTiming {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_millis(500),
+            distance: 4.0,
+        }
+    }
+}
+
+/// How many [`PointerButton::Other`] buttons can be held at once.
+const MAX_OTHER_BUTTONS: usize = 8;
+
+/// Set of pressed [`PointerButton`]s.
+///
+/// Up to eight `Other` buttons are tracked at the same time; presses of further ones are
+/// ignored until one of them is released.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PointerButtons {
+    named: u8,
+    /// Ids of the held `Other` buttons, sorted; only the first `other_len` are used.
+    other: [u16; MAX_OTHER_BUTTONS],
+    other_len: u8,
+}
+
+impl PointerButtons {
+    fn others(&self) -> &[u16] {
+        &self.other[..self.other_len as usize]
+    }
+
+    pub fn contains(self, button: PointerButton) -> bool {
+        match (button.bit(), button) {
+            (Some(bit), _) => self.named & bit != 0,
+            (None, PointerButton::Other(id)) => self.others().binary_search(&id).is_ok(),
+            (None, _) => false,
+        }
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.named == 0 && self.other_len == 0
+    }
+
+    pub fn insert(&mut self, button: PointerButton) {
+        match (button.bit(), button) {
+            (Some(bit), _) => self.named |= bit,
+            (None, PointerButton::Other(id)) => {
+                let len = self.other_len as usize;
+                if let Err(index) = self.others().binary_search(&id)
+                    && len < MAX_OTHER_BUTTONS
+                {
+                    self.other.copy_within(index..len, index + 1);
+                    self.other[index] = id;
+                    self.other_len += 1;
+                }
+            }
+            (None, _) => {}
+        }
+    }
+
+    pub fn remove(&mut self, button: PointerButton) {
+        match (button.bit(), button) {
+            (Some(bit), _) => self.named &= !bit,
+            (None, PointerButton::Other(id)) => {
+                let len = self.other_len as usize;
+                if let Ok(index) = self.others().binary_search(&id) {
+                    self.other.copy_within(index + 1..len, index);
+                    // Keeps unused slots zeroed, so equal sets compare and hash equal.
+                    self.other[len - 1] = 0;
+                    self.other_len -= 1;
+                }
+            }
+            (None, _) => {}
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub control: bool,
+    pub alt: bool,
+    /// Command on macOS, the Windows key elsewhere.
+    pub meta: bool,
+}
+
+impl From<winit::keyboard::ModifiersState> for Modifiers {
+    fn from(state: winit::keyboard::ModifiersState) -> Self {
+        Self {
+            shift: state.shift_key(),
+            control: state.control_key(),
+            alt: state.alt_key(),
+            meta: state.super_key(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScrollDelta {
+    /// Mouse wheel notches.
+    Lines { x: f32, y: f32 },
+    /// Touchpad scrolling, in logical pixels.
+    Pixels { x: f32, y: f32 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyEvent {
+    /// Key after the keyboard layout was applied.
+    pub key: Key,
+    /// Key position on a US keyboard, independent of the layout.
+    pub physical_key: Option<KeyCode>,
+    pub modifiers: Modifiers,
+    pub repeat: bool,
+}
+
+/// Character a letter, digit or punctuation key types on a US layout without modifiers.
+pub fn us_layout_character(code: KeyCode) -> Option<char> {
+    let character = match code {
+        KeyCode::KeyA => 'a',
+        KeyCode::KeyB => 'b',
+        KeyCode::KeyC => 'c',
+        KeyCode::KeyD => 'd',
+        KeyCode::KeyE => 'e',
+        KeyCode::KeyF => 'f',
+        KeyCode::KeyG => 'g',
+        KeyCode::KeyH => 'h',
+        KeyCode::KeyI => 'i',
+        KeyCode::KeyJ => 'j',
+        KeyCode::KeyK => 'k',
+        KeyCode::KeyL => 'l',
+        KeyCode::KeyM => 'm',
+        KeyCode::KeyN => 'n',
+        KeyCode::KeyO => 'o',
+        KeyCode::KeyP => 'p',
+        KeyCode::KeyQ => 'q',
+        KeyCode::KeyR => 'r',
+        KeyCode::KeyS => 's',
+        KeyCode::KeyT => 't',
+        KeyCode::KeyU => 'u',
+        KeyCode::KeyV => 'v',
+        KeyCode::KeyW => 'w',
+        KeyCode::KeyX => 'x',
+        KeyCode::KeyY => 'y',
+        KeyCode::KeyZ => 'z',
+        KeyCode::Digit0 => '0',
+        KeyCode::Digit1 => '1',
+        KeyCode::Digit2 => '2',
+        KeyCode::Digit3 => '3',
+        KeyCode::Digit4 => '4',
+        KeyCode::Digit5 => '5',
+        KeyCode::Digit6 => '6',
+        KeyCode::Digit7 => '7',
+        KeyCode::Digit8 => '8',
+        KeyCode::Digit9 => '9',
+        KeyCode::Minus => '-',
+        KeyCode::Equal => '=',
+        KeyCode::BracketLeft => '[',
+        KeyCode::BracketRight => ']',
+        KeyCode::Backslash => '\\',
+        KeyCode::Semicolon => ';',
+        KeyCode::Quote => '\'',
+        KeyCode::Backquote => '`',
+        KeyCode::Comma => ',',
+        KeyCode::Period => '.',
+        KeyCode::Slash => '/',
+        _ => return None,
+    };
+    Some(character)
+}
+
+/// Logical key.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Key {
+    Named(NamedKey),
+    Character(String),
+    /// Dead key starting a compose sequence, with the character it stands for if known.
+    Dead(Option<char>),
+    Unidentified,
+}
+
+impl From<&winit::keyboard::Key> for Key {
+    fn from(key: &winit::keyboard::Key) -> Self {
+        use winit::keyboard::Key as WinitKey;
+        match key {
+            WinitKey::Named(named) => Key::Named(*named),
+            WinitKey::Character(text) => Key::Character(text.to_string()),
+            WinitKey::Dead(character) => Key::Dead(*character),
+            WinitKey::Unidentified(_) => Key::Unidentified,
+        }
+    }
+}
+
+/// Input method (IME) event, only delivered to windows that allow IME input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImeEvent {
+    Enabled,
+    /// Text being composed, replacing the previous preedit. An empty text clears it.
+    Preedit {
+        text: String,
+        /// Byte range of the cursor or selection inside `text`; `None` hides the cursor.
+        cursor: Option<(usize, usize)>,
+    },
+    /// Composed text to insert; also delivered as [`InputEvent::Text`].
+    Commit(String),
+    Disabled,
+}
+
+impl From<&winit::event::Ime> for ImeEvent {
+    fn from(ime: &winit::event::Ime) -> Self {
+        use winit::event::Ime;
+        match ime {
+            Ime::Enabled => ImeEvent::Enabled,
+            Ime::Preedit(text, cursor) => ImeEvent::Preedit {
+                text: text.clone(),
+                cursor: *cursor,
+            },
+            Ime::Commit(text) => ImeEvent::Commit(text.clone()),
+            Ime::Disabled => ImeEvent::Disabled,
+        }
+    }
+}
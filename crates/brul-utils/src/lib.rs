@@ -2,10 +2,15 @@ pub mod color;
 pub mod config;
 pub mod control;
 pub mod error;
+pub mod input;
 pub mod math;
+pub mod window;
 
 pub use color::*;
 pub use config::*;
 pub use control::*;
 pub use error::*;
 pub use math::*;
+pub use window::*;
+
+pub use winit::window::CursorIcon;
@@ -0,0 +1,153 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use winit::dpi::{LogicalSize, PhysicalSize};
+
+use crate::{Color, Point, Size};
+
+/// Inner size of a window in physical pixels together with its DPI scale factor.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WindowSize {
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f64,
+}
+
+impl WindowSize {
+    pub fn new(physical: PhysicalSize<u32>, scale_factor: f64) -> Self {
+        Self {
+            width: physical.width,
+            height: physical.height,
+            scale_factor,
+        }
+    }
+
+    pub fn physical(&self) -> PhysicalSize<u32> {
+        PhysicalSize::new(self.width, self.height)
+    }
+
+    pub fn logical(&self) -> LogicalSize<f64> {
+        self.physical().to_logical(self.scale_factor)
+    }
+}
+
+/// Backend independent window identifier, allocated by the app side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct WindowId(u64);
+
+impl WindowId {
+    /// The window created when the GUI starts.
+    pub const MAIN: WindowId = WindowId(0);
+
+    pub fn next() -> Self {
+        static COUNTER: AtomicU64 = AtomicU64::new(1);
+        WindowId(COUNTER.fetch_add(1, Ordering::Relaxed))
+    }
+
+    pub fn as_u64(&self) -> u64 {
+        self.0
+    }
+}
+
+/// How a window covers the screen when fullscreen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fullscreen {
+    /// Window sized to the current monitor, no video mode change.
+    Borderless,
+    /// Exclusive fullscreen using the monitor's preferred video mode.
+    Exclusive,
+}
+
+/// Settings used when a window is created. Sizes and positions are logical pixels.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowConfig {
+    pub title: String,
+    pub inner_size: Size,
+    pub min_inner_size: Option<Size>,
+    pub max_inner_size: Option<Size>,
+    /// Outer position of the window; `None` lets the platform decide.
+    pub position: Option<Point>,
+    pub decorations: bool,
+    pub resizable: bool,
+    pub always_on_top: bool,
+    pub transparent: bool,
+    pub fullscreen: Option<Fullscreen>,
+    /// Color the window is cleared with every frame, magenta by default.
+    pub background_color: Color,
+    /// Lets the platform input method (IME) compose text in the window.
+    pub ime_allowed: bool,
+}
+
+impl WindowConfig {
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Applies a runtime change so the config keeps describing the window.
+    pub fn apply(&mut self, update: &WindowUpdate) {
+        match update {
+            WindowUpdate::Title(title) => self.title = title.clone(),
+            WindowUpdate::InnerSize(size) => self.inner_size = *size,
+            WindowUpdate::MinInnerSize(size) => self.min_inner_size = *size,
+            WindowUpdate::MaxInnerSize(size) => self.max_inner_size = *size,
+            WindowUpdate::Position(position) => self.position = Some(*position),
+            WindowUpdate::Decorations(decorations) => self.decorations = *decorations,
+            WindowUpdate::Resizable(resizable) => self.resizable = *resizable,
+            WindowUpdate::AlwaysOnTop(always_on_top) => self.always_on_top = *always_on_top,
+            WindowUpdate::Transparent(transparent) => self.transparent = *transparent,
+            WindowUpdate::Fullscreen(fullscreen) => self.fullscreen = *fullscreen,
+            WindowUpdate::BackgroundColor(color) => self.background_color = *color,
+            WindowUpdate::ImeAllowed(allowed) => self.ime_allowed = *allowed,
+            WindowUpdate::ImeCursorArea { .. }
+            | WindowUpdate::CursorVisible(_)
+            | WindowUpdate::PointerCapture(_) => {}
+        }
+    }
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            title: "BRUL".into(),
+            inner_size: Size::new(800.0, 600.0),
+            min_inner_size: None,
+            max_inner_size: None,
+            position: None,
+            decorations: true,
+            resizable: true,
+            always_on_top: false,
+            transparent: false,
+            fullscreen: None,
+            background_color: Color::rgb(1.0, 0.0, 1.0),
+            ime_allowed: false,
+        }
+    }
+}
+
+/// A single runtime change to an open window.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WindowUpdate {
+    Title(String),
+    InnerSize(Size),
+    MinInnerSize(Option<Size>),
+    MaxInnerSize(Option<Size>),
+    Position(Point),
+    Decorations(bool),
+    Resizable(bool),
+    AlwaysOnTop(bool),
+    Transparent(bool),
+    Fullscreen(Option<Fullscreen>),
+    /// New clear color; the window is redrawn right away.
+    BackgroundColor(Color),
+    ImeAllowed(bool),
+    /// Text cursor area in logical pixels; the IME candidate window is placed next to it.
+    ImeCursorArea {
+        position: Point,
+        size: Size,
+    },
+    CursorVisible(bool),
+    /// Keeps delivering pointer events from outside the window until all buttons are
+    /// released. Ignored when no button is pressed.
+    PointerCapture(bool),
+}
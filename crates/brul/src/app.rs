@@ -1,6 +1,22 @@
-use crate::{State, runtime::RuntimeManager, state::StateManager, window::WindowManager};
-use brul_utils::{AppControlMessage, Config, EVProxy, GuiControlMessage, Result};
-use std::sync::{Arc, mpsc};
+use crate::{
+    State, StateMut,
+    command::Handlers,
+    input::Input,
+    reactive::Memo,
+    runtime::{RuntimeManager, ShutdownReport, Tasks},
+    shortcut::ShortcutRegistry,
+    state::StateManager,
+    window::WindowManager,
+};
+use brul_gui::HeadlessBackend;
+use brul_utils::{
+    AppControlMessage, Config, EVProxy, Error, GuiControlMessage, GuiProxy, Result, WindowId,
+};
+use parking_lot::Mutex;
+use std::{
+    sync::{Arc, mpsc},
+    thread,
+};
 
 mod builder;
 mod core;
@@ -9,7 +25,9 @@ mod handle;
 mod manager;
 
 pub use builder::AppBuilder;
-pub use event_bus::EventBus;
+pub use event_bus::{
+    Backpressure, Event, EventBus, EventDiscriminants, EventStream, EventTarget, ExitRequest,
+};
 pub use handle::AppHandle;
 pub use manager::AppManager;
 
@@ -19,27 +37,84 @@ pub struct AppInner {
     window: WindowManager,
     config: Config,
     event_bus: EventBus,
+    proxy: EVProxy,
+    commands: Handlers,
+    shortcuts: ShortcutRegistry,
+    tasks: Tasks,
+    /// Taken when the app stops, which ends the control loop.
+    control: Mutex<Option<mpsc::Sender<AppControlMessage>>>,
 }
 
 #[non_exhaustive]
 pub struct App {
     handle: AppHandle,
-    event_loop_proxy: EVProxy,
     runtime: RuntimeManager,
     tasks: Vec<Box<dyn Fn(&AppHandle) -> () + Send + 'static>>,
     inner: Arc<AppInner>,
+    control_rx: Option<mpsc::Receiver<AppControlMessage>>,
 }
 
 impl App {
-    pub fn run(mut self) -> Result<()> {
+    /// Runs the GUI until the app exits, then shuts down the background tasks.
+    ///
+    /// The report lists the tasks that had to be aborted because they ignored the shutdown.
+    pub fn run(self) -> Result<ShutdownReport> {
+        self.run_with(
+            |config, tx| {
+                let backend = brul_gui::Backend::new(config, tx)?;
+                let proxy = backend.get_proxy();
+                Ok((backend, proxy))
+            },
+            brul_gui::Backend::run,
+        )
+    }
+
+    /// Runs the app on a [`HeadlessBackend`] that `drive` steps by hand instead of an event
+    /// loop, whatever [`crate::AppBuilder::headless`] was set to.
+    ///
+    /// `drive` can [`HeadlessBackend::inject`] input and [`HeadlessBackend::step`] frames
+    /// deterministically. Once it returns, the windows still open are closed and the app
+    /// shuts down like after [`App::run`]. Events are handled on the app's control thread,
+    /// so they may arrive after the step that caused them.
+    pub fn run_headless<F>(self, drive: F) -> Result<ShutdownReport>
+    where
+        F: FnOnce(&mut HeadlessBackend),
+    {
+        self.run_with(
+            |config, tx| {
+                let backend = HeadlessBackend::new(tx, config.window().clone())?
+                    .with_click_timing(config.click_timing());
+                let proxy = backend.get_proxy();
+                Ok((backend, proxy))
+            },
+            |mut backend| {
+                drive(&mut backend);
+                backend.finish()
+            },
+        )
+    }
+
+    fn run_with<B>(
+        mut self,
+        create: impl FnOnce(&Config, mpsc::Sender<AppControlMessage>) -> Result<(B, GuiProxy)>,
+        drive: impl FnOnce(B) -> Result<()>,
+    ) -> Result<ShutdownReport> {
         tracing::info!("App run");
 
-        let (tx, rx) = mpsc::channel::<AppControlMessage>();
+        let tx = self
+            .inner
+            .control
+            .lock()
+            .clone()
+            .ok_or(Error::AppNotRunning)?;
+        let rx = self
+            .control_rx
+            .take()
+            .expect("control receiver is only taken by run");
         tx.send(AppControlMessage::AppStarted).unwrap();
 
-        let gui_backend = brul_gui::GuiBackend::new(tx.clone())?;
-        let event_loop_proxy = gui_backend.get_proxy();
-        self.event_loop_proxy.set_proxy(event_loop_proxy.clone());
+        let (gui_backend, event_loop_proxy) = create(&self.inner.config, tx)?;
+        self.inner.proxy.set_proxy(event_loop_proxy.clone());
 
         // TODO: do i need tasks later, or i can give ownership?
         let tasks = std::mem::take(&mut self.tasks);
@@ -54,39 +129,112 @@ impl App {
             .collect();
 
         for task in tasks {
-            self.runtime.spawn(async move {
+            self.inner.tasks.spawn(async move {
                 task();
             });
         }
 
-        let handle = self.runtime.spawn(async move {
-            tracing::info!("Event receiver start");
-            while let Ok(event) = rx.recv() {
-                match event {
-                    AppControlMessage::RequestShutdown => {
-                        tracing::info!("Received shutdown event");
-                        break;
-                    }
-                    AppControlMessage::AppStarted => {
-                        tracing::info!("Received app started event");
-                    }
-                    _ => {
-                        tracing::info!("Received unknown event");
+        let inner = Arc::clone(&self.inner);
+        let runtime = self.runtime.handle().clone();
+        // The loop blocks on the control channel, so it gets a thread of its own. Listeners
+        // still run inside the runtime context.
+        let control_loop = thread::Builder::new()
+            .name("brul-control".into())
+            .spawn(move || {
+                let _runtime = runtime.enter();
+                tracing::info!("Event receiver start");
+                let mut ready = false;
+                let mut exiting = false;
+                // Keeps running after the exit is confirmed, to report the windows the GUI
+                // closes, until the GUI is gone.
+                while let Ok(event) = rx.recv() {
+                    match event {
+                        AppControlMessage::RequestShutdown => {
+                            if exiting {
+                                continue;
+                            }
+                            tracing::info!("Received shutdown event");
+                            let request = ExitRequest::default();
+                            inner.event_bus.emit(Event::BeforeExit(request.clone()));
+                            if request.is_prevented() {
+                                tracing::info!("Exit prevented by a listener");
+                                continue;
+                            }
+                            exiting = true;
+                            let result = event_loop_proxy.send_event(GuiControlMessage::Shutdown);
+                            tracing::debug!("Try send shutdown event: {:?}", result);
+                        }
+                        AppControlMessage::AppStarted => {
+                            tracing::info!("Received app started event");
+                            inner.event_bus.emit(Event::AppStarted);
+                        }
+                        AppControlMessage::Resumed => {
+                            tracing::debug!("GUI resumed");
+                            inner.event_bus.emit(Event::Resumed);
+                        }
+                        AppControlMessage::Suspended => {
+                            tracing::debug!("GUI suspended");
+                            inner.event_bus.emit(Event::Suspended);
+                        }
+                        AppControlMessage::WindowCreated(window) => {
+                            tracing::debug!("Window created: {:?}", window);
+                            inner.window.insert(window, inner.config.window().clone());
+                            inner
+                                .event_bus
+                                .emit_to(window, Event::WindowCreated(window));
+                            if window == WindowId::MAIN && !ready {
+                                ready = true;
+                                inner.event_bus.emit(Event::Ready);
+                            }
+                        }
+                        AppControlMessage::WindowClosed(window) => {
+                            tracing::debug!("Window closed: {:?}", window);
+                            inner.window.remove(window);
+                            inner.event_bus.emit_to(window, Event::WindowClosed(window));
+                            inner
+                                .event_bus
+                                .unlisten_target(&EventTarget::Window(window));
+                            inner.shortcuts.remove_window(window);
+                        }
+                        AppControlMessage::WindowResized { window, size } => {
+                            tracing::debug!("Window {:?} resized: {:?}", window, size);
+                            inner.window.set_size(window, size);
+                            inner
+                                .event_bus
+                                .emit_to(window, Event::WindowResized { window, size });
+                        }
+                        AppControlMessage::ScaleFactorChanged { window, size } => {
+                            tracing::debug!("Window {:?} scale factor changed: {:?}", window, size);
+                            inner.window.set_size(window, size);
+                            inner
+                                .event_bus
+                                .emit_to(window, Event::ScaleFactorChanged { window, size });
+                        }
+                        AppControlMessage::Input { window, event } => {
+                            tracing::trace!("Window {:?} input: {:?}", window, event);
+                            inner.window.input(window, &event);
+                            inner.shortcuts.handle_input(&app_handle, window, &event);
+                            inner.event_bus.emit_to(window, Input { window, event });
+                        }
                     }
                 }
-            }
-            // TODO: send shotdown to gui backend
-            let result = event_loop_proxy.send_event(GuiControlMessage::Shutdown);
-
-            tracing::debug!("Try send shutdown event: {:?}", result);
-            tracing::info!("Event loop ended");
-        });
+                tracing::info!("Event loop ended");
+            })?;
 
         tracing::info!("Try run gui eventloop");
-        gui_backend.run()?;
+        let result = drive(gui_backend);
+
+        // With the GUI gone the control loop ends once the last sender is dropped.
+        self.inner.control.lock().take();
+        if control_loop.join().is_err() {
+            tracing::error!("Control loop panicked");
+        }
+        self.inner.event_bus.emit(Event::AppShutdown);
+        let report = self.runtime.shutdown();
+        result?;
         tracing::info!("App ended ok");
 
-        Ok(())
+        Ok(report)
     }
 }
 
@@ -103,18 +251,43 @@ impl AppManager for App {
         self.inner.state.set(state)
     }
 
-    fn state<T: Send + Sync + 'static>(&self) -> State<'_, T> {
+    fn state<T: Send + Sync + 'static>(&self) -> State<T> {
         self.inner.state.get::<T>()
     }
 
-    fn try_state<T: Send + Sync + 'static>(&self) -> Option<State<'_, T>> {
+    fn try_state<T: Send + Sync + 'static>(&self) -> Option<State<T>> {
         self.inner.state.try_get::<T>()
     }
 
+    fn replace<T: Send + Sync + 'static>(&self, state: T) -> Option<State<T>> {
+        self.inner.state.replace(state)
+    }
+
+    fn unmanage<T: Send + Sync + 'static>(&self) -> Option<State<T>> {
+        self.inner.state.remove::<T>()
+    }
+
+    fn state_mut<T: Clone + Send + Sync + 'static>(&self) -> StateMut<T> {
+        self.inner.state.get_mut::<T>()
+    }
+
+    fn try_state_mut<T: Clone + Send + Sync + 'static>(&self) -> Option<StateMut<T>> {
+        self.inner.state.try_get_mut::<T>()
+    }
+
+    fn update<T: Clone + Send + Sync + 'static, R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        self.inner.state.update(f)
+    }
+
+    fn watch_state<T: Send + Sync + 'static>(&self) -> Memo<u64> {
+        self.inner.state.watch::<T>()
+    }
+
+    #[track_caller]
     fn spawn<F>(&self, future: F) -> tokio::task::JoinHandle<()>
     where
         F: Future<Output = ()> + Send + 'static,
     {
-        self.runtime.spawn(future)
+        self.inner.tasks.spawn(future)
     }
 }
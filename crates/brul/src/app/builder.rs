@@ -1,22 +1,37 @@
 use crate::app::EventBus;
 use crate::app::handle::AppHandle;
-use crate::runtime::RuntimeManager;
+use crate::command::Handlers;
+use crate::runtime::{RuntimeConfig, RuntimeManager, ShutdownReport};
+use crate::shortcut::{ShortcutAction, ShortcutRegistry, ShortcutScope};
 use crate::state::StateManager;
 use crate::window::WindowManager;
 use crate::{App, app::AppInner};
-use brul_utils::{Config, EVProxy, Result};
-use std::sync::Arc;
-use std::{any::TypeId, collections::HashMap};
+use brul_utils::{
+    AppControlMessage, Config, EVProxy, Error, GuiMode, Result, WindowConfig, input::ClickTiming,
+};
+use parking_lot::Mutex;
+use std::{
+    panic::AssertUnwindSafe,
+    sync::{Arc, mpsc},
+    time::Duration,
+};
 
 type SetupHookFn = dyn FnOnce(&mut App) -> () + 'static;
+type ManageFn = dyn FnOnce(&StateManager) -> Result<()> + 'static;
+type ListenFn = dyn FnOnce(&AppHandle) + 'static;
 
 #[derive(Default)]
 pub struct AppBuilder {
     config: Config,
-    proxy: EVProxy,
     setup_hooks: Vec<Box<SetupHookFn>>,
-    managed_states: HashMap<TypeId, Box<dyn Send + Sync>>,
+    managed_states: Vec<Box<ManageFn>>,
+    listeners: Vec<Box<ListenFn>>,
     tasks: Vec<Box<dyn Fn(&AppHandle) -> () + Send + 'static>>,
+    commands: Handlers,
+    event_stream_capacity: Option<usize>,
+    runtime: RuntimeConfig,
+    shutdown_grace_period: Option<Duration>,
+    shortcuts: Vec<(String, ShortcutAction)>,
 }
 
 impl AppBuilder {
@@ -31,6 +46,28 @@ impl AppBuilder {
         self
     }
 
+    /// Config of the main window.
+    pub fn window(mut self, window: WindowConfig) -> Self {
+        self.config = self.config.with_window(window);
+        self
+    }
+
+    /// Runs the app without a display server, rendering into offscreen textures.
+    ///
+    /// [`App::run`] then paces frames in real time; [`App::run_headless`] lets the caller
+    /// step them instead.
+    pub fn headless(mut self) -> Self {
+        self.config = self.config.with_gui_mode(GuiMode::Headless);
+        self
+    }
+
+    /// How close presses must be to count as double or triple clicks, see
+    /// [`crate::input::InputEvent::PointerDown`].
+    pub fn click_timing(mut self, click_timing: ClickTiming) -> Self {
+        self.config = self.config.with_click_timing(click_timing);
+        self
+    }
+
     pub fn setup<F>(mut self, setup_fn: F) -> Self
     where
         F: FnOnce(&mut App) -> () + 'static,
@@ -39,12 +76,19 @@ impl AppBuilder {
         self
     }
 
+    /// Manages `state` under its type `S`. Managing the same type twice makes
+    /// [`AppBuilder::build`] fail with [`Error::StateAlreadyManaged`].
     pub fn manage<S>(mut self, state: S) -> Self
     where
         S: Send + Sync + 'static,
     {
-        self.managed_states
-            .insert(TypeId::of::<S>(), Box::new(state));
+        self.managed_states.push(Box::new(move |manager| {
+            if manager.set(state) {
+                Ok(())
+            } else {
+                Err(Error::StateAlreadyManaged(std::any::type_name::<S>()))
+            }
+        }));
         self
     }
 
@@ -56,24 +100,94 @@ impl AppBuilder {
         self
     }
 
-    pub fn add_listener<F, E>(self, _listener: Box<F>) -> Self
+    /// Registers commands generated with [`crate::generate_handlers!`]. Can be called
+    /// multiple times; a later command with the same name replaces the earlier one.
+    pub fn invoke_handler(mut self, handlers: Handlers) -> Self {
+        self.commands.extend(handlers);
+        self
+    }
+
+    /// Binds a global keyboard shortcut, see [`crate::shortcut`]. An invalid or conflicting
+    /// shortcut makes [`AppBuilder::build`] fail.
+    pub fn shortcut(mut self, shortcut: &str, action: ShortcutAction) -> Self {
+        self.shortcuts.push((shortcut.to_owned(), action));
+        self
+    }
+
+    /// Requests exit when Escape is pressed in any window.
+    pub fn quit_on_escape(self) -> Self {
+        self.shortcut(
+            "Escape",
+            ShortcutAction::callback(|app, _| {
+                if let Err(err) = app.request_exit() {
+                    tracing::warn!("Escape could not request exit: {err}");
+                }
+            }),
+        )
+    }
+
+    /// Tokio runtime to run on, a default multi-thread runtime unless configured.
+    pub fn runtime(mut self, runtime: RuntimeConfig) -> Self {
+        self.runtime = runtime;
+        self
+    }
+
+    /// How long background tasks get to stop after the GUI exited, see
+    /// [`AppHandle::shutdown_token`]. Tasks still running afterwards are dropped and logged.
+    pub fn shutdown_grace_period(mut self, grace_period: Duration) -> Self {
+        self.shutdown_grace_period = Some(grace_period);
+        self
+    }
+
+    /// Number of events buffered per event type for [`EventBus::subscribe_stream`]
+    /// consumers before slow ones start missing events.
+    pub fn event_stream_capacity(mut self, capacity: usize) -> Self {
+        self.event_stream_capacity = Some(capacity);
+        self
+    }
+
+    /// Listens to events of type `E` emitted on the app's [`EventBus`], including the
+    /// built-in [`crate::Event`]s.
+    pub fn add_listener<E, F>(mut self, listener: F) -> Self
     where
-        F: Fn(&App, E) + 'static,
         E: Send + Sync + 'static,
+        F: Fn(&AppHandle, &E) + Send + Sync + 'static,
     {
-        // TODO: add listener to App when build
+        self.listeners.push(Box::new(move |app| {
+            // The bus lives inside the app, a strong handle would keep it alive forever.
+            let captured = AssertUnwindSafe((app.downgrade(), listener));
+            app.event_bus().listen::<E, _>(move |event| {
+                let (app, listener) = &*captured;
+                if let Some(app) = app.upgrade() {
+                    listener(&app, event);
+                }
+            });
+        }));
         self
     }
 
-    pub fn build(self) -> App {
+    pub fn build(self) -> Result<App> {
         tracing::info!("Building app");
-        let runtime = RuntimeManager::new();
+        let mut runtime = RuntimeManager::new(self.runtime)?;
+        if let Some(grace_period) = self.shutdown_grace_period {
+            runtime = runtime.with_grace_period(grace_period);
+        }
+        let (control_tx, control_rx) = mpsc::channel::<AppControlMessage>();
 
+        let mut event_bus = EventBus::new().with_tasks(runtime.tasks().clone());
+        if let Some(capacity) = self.event_stream_capacity {
+            event_bus = event_bus.with_stream_capacity(capacity);
+        }
         let inner = Arc::new(AppInner {
             config: self.config,
-            state: StateManager::new(),
+            state: StateManager::new(event_bus.clone()),
             window: WindowManager::default(),
-            event_bus: EventBus::new(),
+            event_bus,
+            proxy: EVProxy::new(),
+            commands: self.commands,
+            shortcuts: ShortcutRegistry::default(),
+            tasks: runtime.tasks().clone(),
+            control: Mutex::new(Some(control_tx)),
         });
 
         let handle = AppHandle::new(Arc::clone(&inner), runtime.handle().clone());
@@ -82,28 +196,38 @@ impl AppBuilder {
 
         let mut app = App {
             runtime,
-            event_loop_proxy: EVProxy::new(),
             handle,
             tasks,
             inner: inner,
+            control_rx: Some(control_rx),
         };
 
-        for (_, state) in self.managed_states {
-            app.inner.state.set(state);
+        for manage in self.managed_states {
+            manage(&app.inner.state)?;
+        }
+
+        for (shortcut, action) in self.shortcuts {
+            app.inner
+                .shortcuts
+                .register(&shortcut, ShortcutScope::Global, action)?;
+        }
+
+        for listen in self.listeners {
+            listen(&app.handle);
         }
 
         for hook in self.setup_hooks {
             hook(&mut app);
         }
 
-        app
+        Ok(app)
     }
 
-    pub fn run(self) -> Result<()> {
-        let app = self.build();
+    pub fn run(self) -> Result<ShutdownReport> {
+        let app = self.build()?;
         tracing::info!("Running app");
         let result = app.run();
         tracing::debug!("App finished with result: {:?}", result);
-        Ok(())
+        result
     }
 }
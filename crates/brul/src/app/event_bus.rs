@@ -1,15 +1,90 @@
+use crate::runtime::Tasks;
+use brul_utils::{WindowId, WindowSize};
 use std::{
+    any::{Any, TypeId},
     collections::HashMap,
-    panic::{RefUnwindSafe, UnwindSafe},
-    sync::{Arc, RwLock, atomic::AtomicU64},
+    panic::{AssertUnwindSafe, RefUnwindSafe, UnwindSafe},
+    pin::Pin,
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+    task::{Context, Poll},
 };
 use strum::{EnumDiscriminants, EnumMessage};
+use tokio::{runtime::Handle, sync::broadcast};
+use tokio_stream::{
+    Stream,
+    wrappers::{BroadcastStream, errors::BroadcastStreamRecvError},
+};
 
 #[derive(Debug, Clone, EnumDiscriminants)]
 #[strum_discriminants(derive(EnumMessage, Hash))]
 pub enum Event {
+    /// [`crate::App::run`] started, the GUI is not up yet.
     AppStarted,
+    /// The main window was created, emitted once.
+    Ready,
+    /// The GUI became active; on desktop this happens once, right before [`Event::Ready`].
+    Resumed,
+    /// The GUI was suspended by the OS; windows may lose their surfaces.
+    Suspended,
+    /// The app is about to exit because the main or last window closed, Escape was pressed or
+    /// [`crate::AppHandle::request_exit`] was called. Listeners can keep it running with
+    /// [`ExitRequest::prevent_exit`].
+    BeforeExit(ExitRequest),
+    /// The GUI event loop ended, emitted last. Background tasks are cancelled right after.
     AppShutdown,
+    WindowCreated(WindowId),
+    WindowClosed(WindowId),
+    /// Window inner size changed, sizes are physical pixels.
+    WindowResized {
+        window: WindowId,
+        size: WindowSize,
+    },
+    /// Window moved to a display with a different DPI.
+    ScaleFactorChanged {
+        window: WindowId,
+        size: WindowSize,
+    },
+    /// Managed state was updated through [`crate::AppManager::update`] or
+    /// [`crate::AppManager::state_mut`].
+    StateChanged {
+        type_id: TypeId,
+        type_name: &'static str,
+    },
+}
+
+/// Exit that [`Event::BeforeExit`] listeners may veto.
+///
+/// Only listeners called synchronously by [`EventBus::emit`] can prevent the exit.
+#[derive(Debug, Clone, Default)]
+pub struct ExitRequest {
+    prevented: Arc<AtomicBool>,
+}
+
+impl ExitRequest {
+    pub fn prevent_exit(&self) {
+        self.prevented.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_prevented(&self) -> bool {
+        self.prevented.load(Ordering::SeqCst)
+    }
+}
+
+/// Receiver of a targeted event, see [`EventBus::emit_to`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum EventTarget {
+    Window(WindowId),
+    /// Any other receiver the app names itself, like a widget or a plugin.
+    Label(String),
+}
+
+impl From<WindowId> for EventTarget {
+    fn from(window: WindowId) -> Self {
+        EventTarget::Window(window)
+    }
 }
 
 #[derive(Clone)]
@@ -18,19 +93,322 @@ struct Handler {
     callback: Arc<dyn Fn(&Event) + UnwindSafe + RefUnwindSafe + Send + Sync + 'static>,
 }
 
+type SharedEvent = Arc<dyn Any + Send + Sync>;
+
+#[derive(Clone)]
+struct Listener {
+    id: u64,
+    /// Removed from the bus before its first call.
+    once: bool,
+    /// `None` for global listeners.
+    target: Option<EventTarget>,
+    callback: Arc<dyn Fn(&SharedEvent) + UnwindSafe + RefUnwindSafe + Send + Sync + 'static>,
+}
+
+/// What an [`EventStream`] does when its consumer falls behind by more than the bus's
+/// stream capacity.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Backpressure {
+    /// Skip the events that were missed and continue with the oldest one still buffered.
+    #[default]
+    DropOldest,
+    /// End the stream, the consumer can not keep up.
+    Close,
+}
+
+const DEFAULT_STREAM_CAPACITY: usize = 64;
+
+/// Clones share the same set of handlers.
+///
+/// Built-in [`Event`]s can be subscribed to per variant with [`EventBus::subscribe`]. Any
+/// other `Send + Sync + 'static` type is an event too: it is [`EventBus::emit`]ted by value
+/// and delivered to the [`EventBus::listen`]ers of that type.
+///
+/// Listeners are global or bound to an [`EventTarget`]. Global listeners see every event;
+/// bound listeners see events emitted to their target and events broadcast with
+/// [`EventBus::emit_all`].
+#[derive(Clone)]
 pub struct EventBus {
     handlers: Arc<RwLock<HashMap<EventDiscriminants, Vec<Handler>>>>,
+    listeners: Arc<RwLock<HashMap<TypeId, Vec<Listener>>>>,
+    /// `broadcast::Sender<Arc<E>>` per event type with open streams.
+    streams: Arc<RwLock<HashMap<TypeId, Box<dyn Any + Send + Sync>>>>,
+    stream_capacity: usize,
+    runtime: Option<Handle>,
+    /// Set for the app's bus: async listeners are app tasks and shutdown waits for them.
+    tasks: Option<Tasks>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self {
+            handlers: Default::default(),
+            listeners: Default::default(),
+            streams: Default::default(),
+            stream_capacity: DEFAULT_STREAM_CAPACITY,
+            runtime: None,
+            tasks: None,
+        }
+    }
 }
 
 impl EventBus {
     pub fn new() -> Self {
-        Self {
-            handlers: Arc::new(RwLock::new(HashMap::new())),
+        Self::default()
+    }
+
+    /// Runs async listeners on `runtime` instead of the runtime `emit` is called from.
+    pub fn with_runtime(mut self, runtime: Handle) -> Self {
+        self.runtime = Some(runtime);
+        self
+    }
+
+    /// Runs async listeners as tracked app tasks.
+    pub(crate) fn with_tasks(mut self, tasks: Tasks) -> Self {
+        self.runtime = Some(tasks.handle().clone());
+        self.tasks = Some(tasks);
+        self
+    }
+
+    /// Number of events buffered per event type for streams, 64 by default.
+    pub fn with_stream_capacity(mut self, capacity: usize) -> Self {
+        self.stream_capacity = capacity.max(1);
+        self
+    }
+
+    /// Delivers `event` to all listeners and streams of `E`; built-in [`Event`]s also reach
+    /// the subscribers of their variant. Shorthand for [`EventBus::emit_all`].
+    pub fn emit<E: Send + Sync + 'static>(&self, event: E) {
+        self.emit_all(event)
+    }
+
+    /// Broadcasts `event` to the global listeners and to the listeners of every target, the
+    /// counterpart of [`EventBus::emit_to`].
+    pub fn emit_all<E: Send + Sync + 'static>(&self, event: E) {
+        self.dispatch(None, event)
+    }
+
+    /// Delivers `event` to the listeners bound to `target` and to global listeners and
+    /// streams; listeners bound to other targets do not see it.
+    pub fn emit_to<E: Send + Sync + 'static>(&self, target: impl Into<EventTarget>, event: E) {
+        self.dispatch(Some(&target.into()), event)
+    }
+
+    fn dispatch<E: Send + Sync + 'static>(&self, target: Option<&EventTarget>, event: E) {
+        if let Some(event) = (&event as &dyn Any).downcast_ref::<Event>() {
+            self.emit_builtin(event);
+        }
+
+        let event = Arc::new(event);
+        if let Some(sender) = self.streams.read().unwrap().get(&TypeId::of::<E>()) {
+            let sender = sender
+                .downcast_ref::<broadcast::Sender<Arc<E>>>()
+                .expect("stream sender is stored under its event type");
+            // Fails only when no stream is open.
+            let _ = sender.send(Arc::clone(&event));
         }
+
+        let listeners = self.take_listeners(TypeId::of::<E>(), target);
+        // Listeners are unwind safe; the event is only read.
+        let event = AssertUnwindSafe(event as SharedEvent);
+        for listener in listeners {
+            if let Err(err) = std::panic::catch_unwind(|| (listener.callback)(&event)) {
+                tracing::error!(
+                    "Error in {} listener: {:?}",
+                    std::any::type_name::<E>(),
+                    err
+                )
+            };
+        }
+    }
+
+    /// Listeners to call for an event of type `type_id` emitted to `target`; matching `once`
+    /// listeners are removed.
+    fn take_listeners(&self, type_id: TypeId, target: Option<&EventTarget>) -> Vec<Listener> {
+        let matches = |listener: &Listener| match (&listener.target, target) {
+            (None, _) | (_, None) => true,
+            (Some(bound), Some(target)) => bound == target,
+        };
+
+        let listeners = self.listeners.read().unwrap();
+        let Some(current) = listeners.get(&type_id) else {
+            return Vec::new();
+        };
+        if !current
+            .iter()
+            .any(|listener| listener.once && matches(listener))
+        {
+            return current.iter().filter(|l| matches(l)).cloned().collect();
+        }
+        drop(listeners);
+
+        // Taken under the write lock, so a `once` listener is called by a single emit.
+        let mut listeners = self.listeners.write().unwrap();
+        let Some(current) = listeners.get_mut(&type_id) else {
+            return Vec::new();
+        };
+        let taken = current.iter().filter(|l| matches(l)).cloned().collect();
+        current.retain(|listener| !(listener.once && matches(listener)));
+        taken
     }
 
-    pub fn emit(&self, event: &Event) {
-        // let handlers = self.handlers.read().unwrap();
+    fn add_listener(&self, type_id: TypeId, listener: Listener) {
+        let mut listeners = self.listeners.write().unwrap();
+        listeners.entry(type_id).or_default().push(listener);
+    }
+
+    /// Calls `callback` with every emitted event of type `E`. Returns an id for
+    /// [`EventBus::unlisten`].
+    pub fn listen<E, F>(&self, callback: F) -> u64
+    where
+        E: Send + Sync + 'static,
+        F: Fn(&E) + UnwindSafe + RefUnwindSafe + Send + Sync + 'static,
+    {
+        self.listen_sync(None, false, callback)
+    }
+
+    /// Calls `callback` with the events of type `E` emitted to `target` or to all.
+    pub fn listen_to<E, F>(&self, target: impl Into<EventTarget>, callback: F) -> u64
+    where
+        E: Send + Sync + 'static,
+        F: Fn(&E) + UnwindSafe + RefUnwindSafe + Send + Sync + 'static,
+    {
+        self.listen_sync(Some(target.into()), false, callback)
+    }
+
+    /// Calls `callback` with the next emitted event of type `E` only.
+    pub fn once<E, F>(&self, callback: F) -> u64
+    where
+        E: Send + Sync + 'static,
+        F: Fn(&E) + UnwindSafe + RefUnwindSafe + Send + Sync + 'static,
+    {
+        self.listen_sync(None, true, callback)
+    }
+
+    fn listen_sync<E, F>(&self, target: Option<EventTarget>, once: bool, callback: F) -> u64
+    where
+        E: Send + Sync + 'static,
+        F: Fn(&E) + UnwindSafe + RefUnwindSafe + Send + Sync + 'static,
+    {
+        let id = generate_id();
+        self.add_listener(
+            TypeId::of::<E>(),
+            Listener {
+                id,
+                once,
+                target,
+                callback: Arc::new(move |event| {
+                    if let Some(event) = event.downcast_ref::<E>() {
+                        callback(event)
+                    }
+                }),
+            },
+        );
+        id
+    }
+
+    /// Spawns `callback` on the tokio runtime for every emitted event of type `E`.
+    ///
+    /// Events are delivered in emit order, but the spawned tasks may run concurrently.
+    pub fn listen_async<E, F, Fut>(&self, callback: F) -> u64
+    where
+        E: Send + Sync + 'static,
+        F: Fn(Arc<E>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.listen_async_inner(None, callback)
+    }
+
+    /// Like [`EventBus::listen_async`], for the events emitted to `target` or to all.
+    pub fn listen_async_to<E, F, Fut>(&self, target: impl Into<EventTarget>, callback: F) -> u64
+    where
+        E: Send + Sync + 'static,
+        F: Fn(Arc<E>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.listen_async_inner(Some(target.into()), callback)
+    }
+
+    fn listen_async_inner<E, F, Fut>(&self, target: Option<EventTarget>, callback: F) -> u64
+    where
+        E: Send + Sync + 'static,
+        F: Fn(Arc<E>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let runtime = self.runtime.clone();
+        // Only spawns, the callback itself runs outside of `catch_unwind`.
+        let tasks = AssertUnwindSafe(self.tasks.clone());
+        let callback = AssertUnwindSafe(callback);
+        let id = generate_id();
+        self.add_listener(
+            TypeId::of::<E>(),
+            Listener {
+                id,
+                once: false,
+                target,
+                callback: Arc::new(move |event| {
+                    let Ok(event) = Arc::clone(event).downcast::<E>() else {
+                        return;
+                    };
+                    let future = (*callback)(event);
+                    if let Some(tasks) = &*tasks {
+                        drop(tasks.spawn(future));
+                        return;
+                    }
+                    match runtime.clone().or_else(|| Handle::try_current().ok()) {
+                        Some(runtime) => drop(runtime.spawn(future)),
+                        None => tracing::error!(
+                            "No tokio runtime to run {} listener on",
+                            std::any::type_name::<E>()
+                        ),
+                    }
+                }),
+            },
+        );
+        id
+    }
+
+    pub fn unlisten<E: Send + Sync + 'static>(&self, id: u64) {
+        let mut listeners = self.listeners.write().unwrap();
+        if let Some(listeners) = listeners.get_mut(&TypeId::of::<E>()) {
+            listeners.retain(|listener| listener.id != id);
+        }
+    }
+
+    /// Drops every listener bound to `target`.
+    pub fn unlisten_target(&self, target: &EventTarget) {
+        let mut listeners = self.listeners.write().unwrap();
+        for listeners in listeners.values_mut() {
+            listeners.retain(|listener| listener.target.as_ref() != Some(target));
+        }
+    }
+
+    /// Stream of the events of type `E` emitted from now on, dropping missed events when
+    /// the consumer falls behind.
+    pub fn subscribe_stream<E: Send + Sync + 'static>(&self) -> EventStream<E> {
+        self.subscribe_stream_with(Backpressure::DropOldest)
+    }
+
+    /// Stream of the events of type `E` emitted from now on.
+    pub fn subscribe_stream_with<E: Send + Sync + 'static>(
+        &self,
+        backpressure: Backpressure,
+    ) -> EventStream<E> {
+        let mut streams = self.streams.write().unwrap();
+        let sender = streams
+            .entry(TypeId::of::<E>())
+            .or_insert_with(|| Box::new(broadcast::channel::<Arc<E>>(self.stream_capacity).0))
+            .downcast_ref::<broadcast::Sender<Arc<E>>>()
+            .expect("stream sender is stored under its event type");
+        EventStream {
+            inner: BroadcastStream::new(sender.subscribe()),
+            backpressure,
+            missed: 0,
+            closed: false,
+        }
+    }
+
+    fn emit_builtin(&self, event: &Event) {
         let discriminant = EventDiscriminants::from(event);
 
         let handlers = {
@@ -54,7 +432,7 @@ impl EventBus {
     {
         let mut handlers = self.handlers.write().unwrap();
         let id = generate_id();
-        handlers.entry(event).or_insert(Vec::new()).push(Handler {
+        handlers.entry(event).or_default().push(Handler {
             id,
             callback: Arc::new(callback),
         });
@@ -69,7 +447,63 @@ impl EventBus {
     }
 }
 
+/// Events of type `E` from [`EventBus::subscribe_stream`].
+pub struct EventStream<E> {
+    inner: BroadcastStream<Arc<E>>,
+    backpressure: Backpressure,
+    missed: u64,
+    closed: bool,
+}
+
+impl<E: Send + Sync + 'static> EventStream<E> {
+    /// Waits for the next event; `None` once the stream ended.
+    pub async fn recv(&mut self) -> Option<Arc<E>> {
+        std::future::poll_fn(|cx| Pin::new(&mut *self).poll_next(cx)).await
+    }
+
+    /// Number of events skipped because the consumer fell behind.
+    pub fn missed(&self) -> u64 {
+        self.missed
+    }
+}
+
+impl<E: Send + Sync + 'static> Stream for EventStream<E> {
+    type Item = Arc<E>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Arc<E>>> {
+        loop {
+            if self.closed {
+                return Poll::Ready(None);
+            }
+            match std::task::ready!(Pin::new(&mut self.inner).poll_next(cx)) {
+                Some(Ok(event)) => return Poll::Ready(Some(event)),
+                Some(Err(BroadcastStreamRecvError::Lagged(count))) => {
+                    self.missed += count;
+                    tracing::warn!(
+                        "{} stream fell behind, {count} events were dropped",
+                        std::any::type_name::<E>()
+                    );
+                    if self.backpressure == Backpressure::Close {
+                        self.closed = true;
+                    }
+                }
+                None => self.closed = true,
+            }
+        }
+    }
+}
+
+impl<E> std::fmt::Debug for EventStream<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventStream")
+            .field("backpressure", &self.backpressure)
+            .field("missed", &self.missed)
+            .field("closed", &self.closed)
+            .finish_non_exhaustive()
+    }
+}
+
 fn generate_id() -> u64 {
     static COUNTER: AtomicU64 = AtomicU64::new(1);
-    COUNTER.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+    COUNTER.fetch_add(1, Ordering::Relaxed)
 }
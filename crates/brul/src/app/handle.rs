@@ -1,13 +1,40 @@
-use std::sync::Arc;
+use std::{
+    panic::{RefUnwindSafe, UnwindSafe},
+    sync::{Arc, Weak},
+};
+
+use brul_utils::{
+    AppControlMessage, Color, CursorIcon, Error, GuiControlMessage, GuiRequest, Reply, Result,
+    WindowConfig, WindowId, WindowSize, WindowUpdate,
+};
+use tokio::{runtime::Handle, sync::oneshot};
+use tokio_util::sync::CancellationToken;
 
-use brul_utils::Color;
-use tokio::runtime::Handle;
+use serde_json::Value;
 
 use crate::{
-    State,
-    app::{AppInner, manager::AppManager},
+    State, StateMut,
+    app::{AppInner, EventBus, EventTarget, manager::AppManager},
+    command::{CommandError, CommandFuture, CommandResult, Invoke},
+    reactive::Memo,
+    shortcut::ShortcutRegistry,
+    window::{WindowHandle, WindowManager},
 };
 
+pub(crate) struct WeakAppHandle {
+    inner: Weak<AppInner>,
+    runtime_handle: Handle,
+}
+
+impl WeakAppHandle {
+    pub(crate) fn upgrade(&self) -> Option<AppHandle> {
+        Some(AppHandle::new(
+            self.inner.upgrade()?,
+            self.runtime_handle.clone(),
+        ))
+    }
+}
+
 #[derive(Clone)]
 pub struct AppHandle {
     inner: Arc<AppInner>,
@@ -22,9 +49,224 @@ impl AppHandle {
         }
     }
 
-    pub fn set_background_color(&self, _color: Color) {
-        // TODO:
-        todo!()
+    pub fn event_bus(&self) -> &EventBus {
+        &self.inner.event_bus
+    }
+
+    /// Emits a user event, see [`EventBus::emit`].
+    pub fn emit<E: Send + Sync + 'static>(&self, event: E) {
+        self.inner.event_bus.emit(event)
+    }
+
+    /// Emits a user event to `target` only, see [`EventBus::emit_to`].
+    pub fn emit_to<E: Send + Sync + 'static>(&self, target: impl Into<EventTarget>, event: E) {
+        self.inner.event_bus.emit_to(target, event)
+    }
+
+    /// Listens to user events of type `E`, see [`EventBus::listen`].
+    pub fn listen<E, F>(&self, callback: F) -> u64
+    where
+        E: Send + Sync + 'static,
+        F: Fn(&E) + UnwindSafe + RefUnwindSafe + Send + Sync + 'static,
+    {
+        self.inner.event_bus.listen(callback)
+    }
+
+    /// Handle that does not keep the app alive, for callbacks stored inside the app.
+    pub(crate) fn downgrade(&self) -> WeakAppHandle {
+        WeakAppHandle {
+            inner: Arc::downgrade(&self.inner),
+            runtime_handle: self.runtime_handle.clone(),
+        }
+    }
+
+    pub fn shortcuts(&self) -> &ShortcutRegistry {
+        &self.inner.shortcuts
+    }
+
+    pub fn windows(&self) -> &WindowManager {
+        &self.inner.window
+    }
+
+    /// Opens a new window and waits until the GUI thread has created it.
+    ///
+    /// The window shows up in [`AppHandle::get_window`] as soon as the request is sent;
+    /// it is removed again if the GUI fails to create it.
+    pub async fn create_window(&self, config: WindowConfig) -> Result<WindowHandle> {
+        let id = WindowId::next();
+        self.inner.window.insert(id, config.clone());
+        let result = self
+            .request(|reply| GuiRequest::CreateWindow { id, config, reply })
+            .await;
+        match result {
+            Ok(size) => {
+                self.inner.window.set_size(id, size);
+                Ok(WindowHandle::new(id, self.clone()))
+            }
+            Err(err) => {
+                self.inner.window.remove(id);
+                Err(err)
+            }
+        }
+    }
+
+    /// Closes window `id` like its close button does.
+    ///
+    /// Closing the main window, or the last one, exits the whole app even while other
+    /// windows are open; it only asks for the exit, so [`crate::Event::BeforeExit`]
+    /// listeners can keep every window open.
+    pub fn close_window(&self, id: WindowId) -> Result<()> {
+        self.send_gui_message(GuiControlMessage::CloseWindow(id))
+    }
+
+    /// Applies a runtime change to a window and forwards it to the GUI thread.
+    pub(crate) fn update_window(&self, id: WindowId, update: WindowUpdate) -> Result<()> {
+        self.inner.window.apply(id, &update);
+        self.send_gui_message(GuiControlMessage::UpdateWindow { id, update })
+    }
+
+    pub async fn set_title(&self, id: WindowId, title: impl Into<String>) -> Result<()> {
+        let title = title.into();
+        self.request(|reply| GuiRequest::SetTitle {
+            id,
+            title: title.clone(),
+            reply,
+        })
+        .await?;
+        self.inner.window.apply(id, &WindowUpdate::Title(title));
+        Ok(())
+    }
+
+    pub async fn set_cursor(&self, id: WindowId, cursor: CursorIcon) -> Result<()> {
+        self.request(|reply| GuiRequest::SetCursor { id, cursor, reply })
+            .await
+    }
+
+    pub fn set_cursor_visible(&self, id: WindowId, visible: bool) -> Result<()> {
+        self.update_window(id, WindowUpdate::CursorVisible(visible))
+    }
+
+    /// Keeps sending pointer events of window `id` while the pointer is outside of it, until
+    /// all buttons are released. Meant to be called from a pointer down handler to track
+    /// drags; without a pressed button it does nothing.
+    pub fn set_pointer_capture(&self, id: WindowId, captured: bool) -> Result<()> {
+        self.update_window(id, WindowUpdate::PointerCapture(captured))
+    }
+
+    pub async fn request_redraw(&self, id: WindowId) -> Result<()> {
+        self.request(|reply| GuiRequest::RequestRedraw { id, reply })
+            .await
+    }
+
+    /// Last known size of the main window, both physical and logical via [`WindowSize`].
+    pub fn window_size(&self) -> Option<WindowSize> {
+        self.inner.window.size(WindowId::MAIN)
+    }
+
+    /// Current size of window `id` as reported by the GUI thread, unlike the cached
+    /// [`WindowManager::size`].
+    pub async fn inner_size(&self, id: WindowId) -> Result<WindowSize> {
+        let size = self
+            .request(|reply| GuiRequest::WindowSize { id, reply })
+            .await?;
+        self.inner.window.set_size(id, size);
+        Ok(size)
+    }
+
+    async fn request<T>(&self, request: impl FnOnce(Reply<T>) -> GuiRequest) -> Result<T> {
+        self.inner.proxy.request(request).await
+    }
+
+    /// Runs the command registered as `command` with a JSON `payload` on the app runtime.
+    ///
+    /// The payload is an object with a field per argument; the result is the command's
+    /// serialized return value.
+    pub async fn invoke(&self, command: &str, payload: Value) -> Result<Value> {
+        self.dispatch(Invoke::new(self.clone(), command, payload))
+            .await
+            .map_err(|err| err.into_error(command))
+    }
+
+    /// Runs a prepared invocation on the app runtime and waits for its result.
+    ///
+    /// The command runs as an app task, so shutdown waits for it like for
+    /// [`AppManager::spawn`]ed tasks. An invocation tied to a window that is already closed
+    /// is cancelled right away.
+    pub async fn dispatch(&self, invoke: Invoke) -> CommandResult {
+        let future: CommandFuture = match invoke.window() {
+            Some(window) => {
+                let Some(token) = self.inner.window.command_token(window) else {
+                    return Err(CommandError::Cancelled);
+                };
+                // The command runs with the window's token; the caller's token still cancels it.
+                let caller = invoke.cancellation().clone();
+                let future = self
+                    .inner
+                    .commands
+                    .dispatch(&invoke.with_cancellation(token.clone()));
+                Box::pin(async move {
+                    tokio::select! {
+                        result = future => result,
+                        _ = caller.cancelled() => {
+                            token.cancel();
+                            Err(CommandError::Cancelled)
+                        }
+                    }
+                })
+            }
+            None => self.inner.commands.dispatch(&invoke),
+        };
+
+        let (result_tx, result_rx) = oneshot::channel();
+        let task = self.inner.tasks.spawn(async move {
+            let _ = result_tx.send(future.await);
+        });
+        match task.await {
+            Ok(()) => result_rx.await.unwrap_or(Err(CommandError::Cancelled)),
+            Err(err) if err.is_panic() => {
+                Err(CommandError::Failed(format!("command panicked: {err}")))
+            }
+            // Aborted because it outlived the shutdown grace period.
+            Err(_) => Err(CommandError::Cancelled),
+        }
+    }
+
+    /// Asks the app to exit, as if the main window was closed. [`crate::Event::BeforeExit`]
+    /// listeners can still prevent it.
+    pub fn request_exit(&self) -> Result<()> {
+        self.inner
+            .control
+            .lock()
+            .as_ref()
+            .ok_or(Error::AppNotRunning)?
+            .send(AppControlMessage::RequestShutdown)
+            .map_err(|_| Error::AppNotRunning)
+    }
+
+    /// Cancelled when the app exits. Long running tasks should stop once it fires; they
+    /// get the [`crate::AppBuilder::shutdown_grace_period`] to do so.
+    pub fn shutdown_token(&self) -> CancellationToken {
+        self.inner.tasks.shutdown_token()
+    }
+
+    pub(crate) fn send_gui_message(&self, message: GuiControlMessage) -> Result<()> {
+        self.inner.proxy.send(message)
+    }
+
+    pub fn get_window(&self, id: WindowId) -> Option<WindowHandle> {
+        self.inner
+            .window
+            .contains(id)
+            .then(|| WindowHandle::new(id, self.clone()))
+    }
+
+    pub fn main_window(&self) -> Option<WindowHandle> {
+        self.get_window(WindowId::MAIN)
+    }
+
+    /// Changes the clear color of the main window and redraws it.
+    pub fn set_background_color(&self, color: Color) -> Result<()> {
+        self.update_window(WindowId::MAIN, WindowUpdate::BackgroundColor(color))
     }
 }
 
@@ -41,18 +283,43 @@ impl AppManager for AppHandle {
         self.inner.state.set(state)
     }
 
-    fn state<T: Send + Sync + 'static>(&self) -> State<'_, T> {
+    fn state<T: Send + Sync + 'static>(&self) -> State<T> {
         self.inner.state.get::<T>()
     }
 
-    fn try_state<T: Send + Sync + 'static>(&self) -> Option<State<'_, T>> {
+    fn try_state<T: Send + Sync + 'static>(&self) -> Option<State<T>> {
         self.inner.state.try_get::<T>()
     }
 
+    fn replace<T: Send + Sync + 'static>(&self, state: T) -> Option<State<T>> {
+        self.inner.state.replace(state)
+    }
+
+    fn unmanage<T: Send + Sync + 'static>(&self) -> Option<State<T>> {
+        self.inner.state.remove::<T>()
+    }
+
+    fn state_mut<T: Clone + Send + Sync + 'static>(&self) -> StateMut<T> {
+        self.inner.state.get_mut::<T>()
+    }
+
+    fn try_state_mut<T: Clone + Send + Sync + 'static>(&self) -> Option<StateMut<T>> {
+        self.inner.state.try_get_mut::<T>()
+    }
+
+    fn update<T: Clone + Send + Sync + 'static, R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        self.inner.state.update(f)
+    }
+
+    fn watch_state<T: Send + Sync + 'static>(&self) -> Memo<u64> {
+        self.inner.state.watch::<T>()
+    }
+
+    #[track_caller]
     fn spawn<F>(&self, future: F) -> tokio::task::JoinHandle<()>
     where
         F: Future<Output = ()> + Send + 'static,
     {
-        self.runtime_handle.spawn(future)
+        self.inner.tasks.spawn(future)
     }
 }
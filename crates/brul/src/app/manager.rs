@@ -1,4 +1,4 @@
-use crate::{State, app::handle::AppHandle};
+use crate::{State, StateMut, app::handle::AppHandle, reactive::Memo};
 use brul_utils::Config;
 use tokio::task::JoinHandle;
 
@@ -11,10 +11,36 @@ pub trait AppManager {
 
     fn manage<T: Send + Sync + 'static>(&mut self, state: T) -> bool;
 
-    fn state<T: Send + Sync + 'static>(&self) -> State<'_, T>;
+    /// Snapshot of managed state; it does not follow later updates, see [`State`].
+    fn state<T: Send + Sync + 'static>(&self) -> State<T>;
 
-    fn try_state<T: Send + Sync + 'static>(&self) -> Option<State<'_, T>>;
+    fn try_state<T: Send + Sync + 'static>(&self) -> Option<State<T>>;
 
+    /// Manages `state`, or swaps it in for the current value of the same type.
+    ///
+    /// Returns the previous value and emits [`crate::Event::StateChanged`] if there was one.
+    fn replace<T: Send + Sync + 'static>(&self, state: T) -> Option<State<T>>;
+
+    /// Stops managing `T` and returns its last value.
+    fn unmanage<T: Send + Sync + 'static>(&self) -> Option<State<T>>;
+
+    /// Write access to managed state; listeners are notified when the guard is dropped, if
+    /// it was written through.
+    ///
+    /// The value is cloned on first write while [`State`] handles to it are alive.
+    fn state_mut<T: Clone + Send + Sync + 'static>(&self) -> StateMut<T>;
+
+    fn try_state_mut<T: Clone + Send + Sync + 'static>(&self) -> Option<StateMut<T>>;
+
+    /// Mutates managed state and emits [`crate::Event::StateChanged`].
+    fn update<T: Clone + Send + Sync + 'static, R>(&self, f: impl FnOnce(&mut T) -> R) -> R;
+
+    /// Reactive revision of managed state, increased by every update.
+    fn watch_state<T: Send + Sync + 'static>(&self) -> Memo<u64>;
+
+    /// Spawns a background task on the app runtime. The app waits for it on exit, see
+    /// [`AppHandle::shutdown_token`].
+    #[track_caller]
     fn spawn<F>(&self, future: F) -> JoinHandle<()>
     where
         F: Future<Output = ()> + Send + 'static;
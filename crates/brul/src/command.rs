@@ -0,0 +1,342 @@
+//! Commands: functions marked with [`macro@crate::command`] that are invoked by name with
+//! a JSON payload.
+
+use crate::{AppHandle, AppManager, State};
+use brul_utils::{Error, WindowId};
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::Value;
+use std::{collections::HashMap, ops::Deref, pin::Pin, time::Duration};
+use tokio_util::sync::CancellationToken;
+
+#[derive(Debug, thiserror::Error)]
+pub enum CommandError {
+    #[error("command `{0}` is not registered")]
+    NotFound(String),
+
+    #[error("state of type {0} is not managed")]
+    MissingState(&'static str),
+
+    #[error("invalid argument `{arg}`: {source}")]
+    InvalidArgument {
+        arg: &'static str,
+        source: serde_json::Error,
+    },
+
+    #[error("failed to serialize the response: {0}")]
+    InvalidResponse(serde_json::Error),
+
+    #[error("{0}")]
+    Failed(String),
+
+    #[error("command was cancelled")]
+    Cancelled,
+
+    #[error("command timed out after {0:?}")]
+    TimedOut(Duration),
+}
+
+impl CommandError {
+    /// Converts the error into the app-wide [`Error`] for the command named `command`.
+    pub fn into_error(self, command: &str) -> Error {
+        let command = command.to_owned();
+        match self {
+            CommandError::NotFound(name) => Error::UnknownCommand(name),
+            CommandError::InvalidArgument { arg, source } => Error::InvalidCommandArgument {
+                command,
+                arg,
+                message: source.to_string(),
+            },
+            CommandError::Cancelled => Error::CommandCancelled(command),
+            CommandError::TimedOut(timeout) => Error::CommandTimedOut { command, timeout },
+            err @ (CommandError::MissingState(_)
+            | CommandError::InvalidResponse(_)
+            | CommandError::Failed(_)) => Error::CommandFailed {
+                command,
+                message: err.to_string(),
+            },
+        }
+    }
+}
+
+pub type CommandResult = Result<Value, CommandError>;
+
+pub type CommandFuture = Pin<Box<dyn Future<Output = CommandResult> + Send + 'static>>;
+
+/// Generated wrapper around a command function. Arguments are extracted right away; the
+/// returned future runs the command.
+pub type CommandHandler = fn(&Invoke) -> CommandFuture;
+
+/// A registered command: its handler and an optional timeout.
+#[derive(Debug, Clone, Copy)]
+pub struct Command {
+    handler: CommandHandler,
+    timeout: Option<Duration>,
+}
+
+impl Command {
+    pub const fn new(handler: CommandHandler) -> Self {
+        Self {
+            handler,
+            timeout: None,
+        }
+    }
+
+    /// Cancels the command and fails it with [`CommandError::TimedOut`] after `timeout`.
+    pub const fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+}
+
+/// A single command invocation: the command name, its payload and the app it runs in.
+pub struct Invoke {
+    command: String,
+    payload: Value,
+    app: AppHandle,
+    window: Option<WindowId>,
+    cancellation: CancellationToken,
+}
+
+impl Invoke {
+    pub fn new(app: AppHandle, command: impl Into<String>, payload: Value) -> Self {
+        Self {
+            command: command.into(),
+            payload,
+            app,
+            window: None,
+            cancellation: CancellationToken::new(),
+        }
+    }
+
+    /// Ties the invocation to `window`: closing the window cancels the command.
+    pub fn with_window(mut self, window: WindowId) -> Self {
+        self.window = Some(window);
+        self
+    }
+
+    /// Uses `token` to cancel the command instead of a fresh token.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = token;
+        self
+    }
+
+    pub fn window(&self) -> Option<WindowId> {
+        self.window
+    }
+
+    pub fn cancellation(&self) -> &CancellationToken {
+        &self.cancellation
+    }
+
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+
+    pub fn payload(&self) -> &Value {
+        &self.payload
+    }
+
+    pub fn app_handle(&self) -> &AppHandle {
+        &self.app
+    }
+}
+
+/// Something a command can take as an argument.
+pub trait CommandArg: Sized {
+    /// Extracts the argument called `name` from the invocation.
+    fn from_invoke(name: &'static str, invoke: &Invoke) -> Result<Self, CommandError>;
+}
+
+impl CommandArg for AppHandle {
+    fn from_invoke(_name: &'static str, invoke: &Invoke) -> Result<Self, CommandError> {
+        Ok(invoke.app.clone())
+    }
+}
+
+/// Cancellation of the running command, taken as a command argument.
+///
+/// Cancelled when the command's window closes, its timeout expires or the caller cancels it.
+#[derive(Debug, Clone)]
+pub struct Cancellation(CancellationToken);
+
+impl Cancellation {
+    pub fn into_token(self) -> CancellationToken {
+        self.0
+    }
+}
+
+impl Deref for Cancellation {
+    type Target = CancellationToken;
+
+    fn deref(&self) -> &CancellationToken {
+        &self.0
+    }
+}
+
+impl CommandArg for Cancellation {
+    fn from_invoke(_name: &'static str, invoke: &Invoke) -> Result<Self, CommandError> {
+        Ok(Cancellation(invoke.cancellation.clone()))
+    }
+}
+
+impl<T: Send + Sync + 'static> CommandArg for State<T> {
+    fn from_invoke(_name: &'static str, invoke: &Invoke) -> Result<Self, CommandError> {
+        invoke
+            .app
+            .try_state::<T>()
+            .ok_or(CommandError::MissingState(std::any::type_name::<T>()))
+    }
+}
+
+/// Payload arguments are read from the payload object's field of the same name; a missing
+/// field deserializes from `null`, so `Option` arguments may be left out.
+impl<T: DeserializeOwned> CommandArg for T {
+    fn from_invoke(name: &'static str, invoke: &Invoke) -> Result<Self, CommandError> {
+        let value = invoke.payload.get(name).unwrap_or(&Value::Null);
+        T::deserialize(value).map_err(|source| CommandError::InvalidArgument { arg: name, source })
+    }
+}
+
+/// Runs the body of a synchronous command on tokio's blocking pool.
+///
+/// The body only starts once the returned future is polled, so it runs on the app runtime
+/// and its timeout and cancellation apply. A cancelled command resolves right away; the
+/// body itself can not be interrupted and its result is dropped.
+#[doc(hidden)]
+pub fn run_blocking(body: impl FnOnce() -> CommandResult + Send + 'static) -> CommandFuture {
+    Box::pin(async move {
+        match tokio::task::spawn_blocking(body).await {
+            Ok(result) => result,
+            Err(err) => match err.try_into_panic() {
+                Ok(panic) => std::panic::resume_unwind(panic),
+                Err(_) => Err(CommandError::Cancelled),
+            },
+        }
+    })
+}
+
+/// Serializes a command's return value.
+pub fn to_response<T: Serialize>(value: T) -> CommandResult {
+    serde_json::to_value(value).map_err(CommandError::InvalidResponse)
+}
+
+/// How `#[brul::command]` turns the return value of a command into a [`CommandResult`].
+///
+/// The choice is made on the type, not on how it is spelled: `(&value).response_kind()`
+/// resolves to [`ResultKind`] for a `Result` whose error implements `Display`, aliases
+/// included, and falls back to [`ValueKind`] for any other serializable value.
+#[doc(hidden)]
+pub mod response {
+    use super::{CommandError, CommandResult, to_response};
+    use serde::Serialize;
+    use std::fmt::Display;
+
+    pub struct ResultTag;
+
+    impl ResultTag {
+        pub fn response<T: Serialize, E: Display>(self, result: Result<T, E>) -> CommandResult {
+            match result {
+                Ok(value) => to_response(value),
+                Err(err) => Err(CommandError::Failed(err.to_string())),
+            }
+        }
+    }
+
+    pub trait ResultKind {
+        fn response_kind(&self) -> ResultTag {
+            ResultTag
+        }
+    }
+
+    impl<T: Serialize, E: Display> ResultKind for Result<T, E> {}
+
+    pub struct ValueTag;
+
+    impl ValueTag {
+        pub fn response<T: Serialize>(self, value: T) -> CommandResult {
+            to_response(value)
+        }
+    }
+
+    pub trait ValueKind {
+        fn response_kind(&self) -> ValueTag {
+            ValueTag
+        }
+    }
+
+    impl<T: Serialize> ValueKind for &T {}
+}
+
+/// Command table built by [`crate::generate_handlers!`].
+#[derive(Default, Clone)]
+pub struct Handlers {
+    handlers: HashMap<&'static str, Command>,
+}
+
+impl Handlers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `handler` as `name`, replacing a previous command with the same name.
+    pub fn with(mut self, name: &'static str, command: Command) -> Self {
+        self.handlers.insert(name, command);
+        self
+    }
+
+    pub(crate) fn extend(&mut self, other: Handlers) {
+        self.handlers.extend(other.handlers);
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.handlers.contains_key(name)
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.handlers.keys().copied().collect()
+    }
+
+    /// Starts the command named by `invoke`.
+    ///
+    /// The future resolves to [`CommandError::Cancelled`] as soon as the invocation's token
+    /// is cancelled, and cancels the token itself when the command's timeout expires.
+    pub fn dispatch(&self, invoke: &Invoke) -> CommandFuture {
+        let Some(command) = self.handlers.get(invoke.command()) else {
+            let err = CommandError::NotFound(invoke.command.clone());
+            return Box::pin(std::future::ready(Err(err)));
+        };
+
+        let cancellation = invoke.cancellation.clone();
+        let timeout = command.timeout;
+        let future = (command.handler)(invoke);
+        Box::pin(async move {
+            let run = async {
+                let Some(timeout) = timeout else {
+                    return future.await;
+                };
+                match tokio::time::timeout(timeout, future).await {
+                    Ok(result) => result,
+                    Err(_) => {
+                        cancellation.cancel();
+                        Err(CommandError::TimedOut(timeout))
+                    }
+                }
+            };
+            tokio::select! {
+                biased;
+                _ = cancellation.cancelled() => Err(CommandError::Cancelled),
+                result = run => result,
+            }
+        })
+    }
+}
+
+impl std::fmt::Debug for Handlers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.handlers.keys()).finish()
+    }
+}
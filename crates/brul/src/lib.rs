@@ -1,13 +1,34 @@
+extern crate self as brul;
+
 mod app;
+pub mod command;
+pub mod reactive;
 mod runtime;
+pub mod shortcut;
 mod state;
 mod window;
 
-pub use app::{App, AppBuilder, AppHandle, AppManager};
-pub use brul_macro::command;
+pub use app::{
+    App, AppBuilder, AppHandle, AppManager, Backpressure, Event, EventBus, EventDiscriminants,
+    EventStream, EventTarget, ExitRequest,
+};
+pub use brul_macro::{command, generate_handlers};
 pub use brul_utils::Error;
-pub use state::State;
+pub use runtime::{RuntimeConfig, ShutdownReport};
+pub use state::{State, StateMut};
+pub use window::{WindowHandle, WindowManager};
 
 pub mod util {
     pub use brul_utils::*;
 }
+
+/// Display-less GUI backend for tests, see [`App::run_headless`].
+pub mod headless {
+    pub use brul_gui::{HeadlessBackend, SyntheticEvent};
+}
+
+/// Keyboard, pointer and window input, emitted on the [`EventBus`] as
+/// [`input::Input`] targeted at the window it happened in.
+pub mod input {
+    pub use brul_utils::input::*;
+}
@@ -0,0 +1,18 @@
+//! Fine-grained reactivity: [`Signal`]s hold values, [`Memo`]s derive from them and
+//! [`Effect`]s react to changes.
+//!
+//! Dependencies are tracked automatically by reading signals and memos inside a memo or
+//! effect. A write marks everything downstream as stale and then runs the affected effects
+//! once each; memos are recomputed before their dependents, so an effect never observes a
+//! mix of old and new values. All types are `Send + Sync` and can be used from the GUI
+//! thread as well as from tasks started with [`crate::AppManager::spawn`].
+
+mod effect;
+mod memo;
+mod runtime;
+mod signal;
+
+pub use effect::Effect;
+pub use memo::Memo;
+pub use runtime::{batch, untrack};
+pub use signal::Signal;
@@ -0,0 +1,63 @@
+use super::runtime::{self, Node, NodeCore, NodeState};
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+type EffectFn = Box<dyn FnMut() + Send>;
+
+struct EffectInner {
+    core: NodeCore,
+    f: Mutex<EffectFn>,
+}
+
+impl fmt::Debug for EffectInner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Effect").finish_non_exhaustive()
+    }
+}
+
+impl Node for EffectInner {
+    fn core(&self) -> &NodeCore {
+        &self.core
+    }
+
+    fn compute(&self) -> bool {
+        let mut f = self.f.lock().unwrap_or_else(|err| err.into_inner());
+        f();
+        false
+    }
+}
+
+/// Side effect re-run whenever a signal or memo it read has changed.
+///
+/// Runs once on creation. The effect stays active until the handle is dropped or
+/// [`Effect::dispose`] is called.
+#[must_use = "the effect stops when the handle is dropped"]
+#[derive(Debug)]
+pub struct Effect {
+    inner: Arc<EffectInner>,
+}
+
+impl Effect {
+    pub fn new(f: impl FnMut() + Send + 'static) -> Self {
+        let inner = Arc::new(EffectInner {
+            core: NodeCore::new(NodeState::Dirty, true),
+            f: Mutex::new(Box::new(f)),
+        });
+        let node: Arc<dyn Node> = inner.clone();
+        // Writes made by the first run schedule other effects, which run after it.
+        runtime::batch(|| runtime::update_if_necessary(&node));
+        Self { inner }
+    }
+
+    /// Stops the effect; it is not run again.
+    pub fn dispose(self) {}
+}
+
+impl Drop for Effect {
+    fn drop(&mut self) {
+        let node: Arc<dyn Node> = self.inner.clone();
+        runtime::dispose(&node);
+    }
+}
@@ -0,0 +1,103 @@
+use super::runtime::{self, Node, NodeCore, NodeState};
+use std::{
+    fmt,
+    sync::{Arc, RwLock},
+};
+
+type MemoFn<T> = Box<dyn Fn() -> T + Send + Sync>;
+
+struct MemoInner<T> {
+    core: NodeCore,
+    value: RwLock<Option<T>>,
+    f: MemoFn<T>,
+}
+
+impl<T> fmt::Debug for MemoInner<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Memo").finish_non_exhaustive()
+    }
+}
+
+impl<T: PartialEq + Send + Sync + 'static> Node for MemoInner<T> {
+    fn core(&self) -> &NodeCore {
+        &self.core
+    }
+
+    fn compute(&self) -> bool {
+        let value = (self.f)();
+        let mut current = self.value.write().unwrap_or_else(|err| err.into_inner());
+        if current.as_ref() == Some(&value) {
+            return false;
+        }
+        *current = Some(value);
+        true
+    }
+}
+
+/// Value derived from signals and other memos.
+///
+/// Computed lazily on first read and recomputed only when a dependency changed. When the
+/// result equals the previous one, dependents are not updated.
+pub struct Memo<T> {
+    inner: Arc<MemoInner<T>>,
+}
+
+impl<T: PartialEq + Send + Sync + 'static> Memo<T> {
+    pub fn new(f: impl Fn() -> T + Send + Sync + 'static) -> Self {
+        Self {
+            inner: Arc::new(MemoInner {
+                core: NodeCore::new(NodeState::Dirty, false),
+                value: RwLock::new(None),
+                f: Box::new(f),
+            }),
+        }
+    }
+
+    /// Reads the value through `f`, tracking the memo as a dependency.
+    pub fn with<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        let node: Arc<dyn Node> = self.inner.clone();
+        // Tracked first, so a write after the update marks the reader.
+        runtime::track(Arc::clone(&node));
+        runtime::update_if_necessary(&node);
+
+        let value = self
+            .inner
+            .value
+            .read()
+            .unwrap_or_else(|err| err.into_inner());
+        // Empty only when the first computation panicked and nothing changed since.
+        f(value.as_ref().expect("memo computation panicked"))
+    }
+
+    pub fn get(&self) -> T
+    where
+        T: Clone,
+    {
+        self.with(T::clone)
+    }
+
+    /// Reads the value without tracking it.
+    pub fn get_untracked(&self) -> T
+    where
+        T: Clone,
+    {
+        runtime::untrack(|| self.get())
+    }
+}
+
+impl<T> Clone for Memo<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Memo<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.inner.value.try_read() {
+            Ok(value) => f.debug_tuple("Memo").field(&*value).finish(),
+            Err(_) => f.debug_tuple("Memo").field(&"<locked>").finish(),
+        }
+    }
+}
@@ -0,0 +1,419 @@
+use std::{
+    cell::{Cell, RefCell},
+    panic::AssertUnwindSafe,
+    sync::{Arc, Mutex, MutexGuard, Weak},
+};
+
+/// Freshness of a derived node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum NodeState {
+    Clean,
+    /// A transitive source changed; direct sources have to be checked first.
+    Check,
+    /// A direct source changed; the node has to recompute.
+    Dirty,
+}
+
+#[derive(Debug)]
+struct Links {
+    state: NodeState,
+    sources: Vec<Arc<dyn Node>>,
+    observers: Vec<Weak<dyn Node>>,
+    disposed: bool,
+    /// Number of threads bringing the node up to date.
+    updating: usize,
+    /// Strongest mark received while updating; the update runs again to pick it up.
+    remarked: Option<NodeState>,
+}
+
+/// Graph bookkeeping shared by signals, memos and effects.
+#[derive(Debug)]
+pub(crate) struct NodeCore {
+    effect: bool,
+    links: Mutex<Links>,
+}
+
+impl NodeCore {
+    pub(crate) fn new(state: NodeState, effect: bool) -> Self {
+        Self {
+            effect,
+            links: Mutex::new(Links {
+                state,
+                sources: Vec::new(),
+                observers: Vec::new(),
+                disposed: false,
+                updating: 0,
+                remarked: None,
+            }),
+        }
+    }
+
+    fn links(&self) -> MutexGuard<'_, Links> {
+        self.links.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn observers(&self) -> Vec<Arc<dyn Node>> {
+        let mut links = self.links();
+        links
+            .observers
+            .retain(|observer| observer.strong_count() > 0);
+        links.observers.iter().filter_map(Weak::upgrade).collect()
+    }
+
+    fn add_observer(&self, observer: Weak<dyn Node>) {
+        let mut links = self.links();
+        if !links
+            .observers
+            .iter()
+            .any(|known| same_weak(known, &observer))
+        {
+            links.observers.push(observer);
+        }
+    }
+
+    fn remove_observer(&self, observer: &Weak<dyn Node>) {
+        self.links()
+            .observers
+            .retain(|known| !same_weak(known, observer));
+    }
+}
+
+/// A node of the dependency graph.
+pub(crate) trait Node: Send + Sync + std::fmt::Debug {
+    fn core(&self) -> &NodeCore;
+
+    /// Recomputes the node's value and reports whether it changed. Never called for signals.
+    fn compute(&self) -> bool;
+}
+
+/// The computation running on a thread and the sources it read so far.
+struct Tracker {
+    observer: Weak<dyn Node>,
+    sources: Vec<Arc<dyn Node>>,
+}
+
+thread_local! {
+    static TRACKER: RefCell<Option<Tracker>> = const { RefCell::new(None) };
+    static BATCH_DEPTH: Cell<usize> = const { Cell::new(0) };
+    static FLUSHING: Cell<bool> = const { Cell::new(false) };
+    /// Effects waiting for the batch of this thread to end.
+    ///
+    /// Effects run on the thread that wrote the signal, wherever they were created. Keeping
+    /// the queue per thread stops a flush on one thread from running effects another thread
+    /// queued inside its open batch.
+    static PENDING: RefCell<Vec<Arc<dyn Node>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Swaps the tracker of this thread and puts the previous one back when dropped, also
+/// when the computation panics.
+struct TrackerScope {
+    previous: Option<Option<Tracker>>,
+}
+
+impl TrackerScope {
+    fn enter(tracker: Option<Tracker>) -> Self {
+        Self {
+            previous: Some(TRACKER.replace(tracker)),
+        }
+    }
+
+    /// Restores the previous tracker and returns the sources recorded in the scope.
+    fn exit(mut self) -> Vec<Arc<dyn Node>> {
+        TRACKER
+            .replace(self.previous.take().flatten())
+            .map(|tracker| tracker.sources)
+            .unwrap_or_default()
+    }
+}
+
+impl Drop for TrackerScope {
+    fn drop(&mut self) {
+        if let Some(previous) = self.previous.take() {
+            TRACKER.set(previous);
+        }
+    }
+}
+
+/// Holds a batch open on this thread until dropped.
+struct BatchScope;
+
+impl BatchScope {
+    fn enter() -> Self {
+        BATCH_DEPTH.set(BATCH_DEPTH.get() + 1);
+        Self
+    }
+}
+
+impl Drop for BatchScope {
+    fn drop(&mut self) {
+        BATCH_DEPTH.set(BATCH_DEPTH.get() - 1);
+    }
+}
+
+/// Marks this thread as flushing until dropped.
+struct FlushScope;
+
+impl FlushScope {
+    fn enter() -> Self {
+        FLUSHING.set(true);
+        Self
+    }
+}
+
+impl Drop for FlushScope {
+    fn drop(&mut self) {
+        FLUSHING.set(false);
+    }
+}
+
+fn same(a: &Arc<dyn Node>, b: &Arc<dyn Node>) -> bool {
+    std::ptr::addr_eq(Arc::as_ptr(a), Arc::as_ptr(b))
+}
+
+fn same_weak(a: &Weak<dyn Node>, b: &Weak<dyn Node>) -> bool {
+    std::ptr::addr_eq(a.as_ptr(), b.as_ptr())
+}
+
+/// Records `node` as a source of the computation currently running on this thread.
+///
+/// The computation observes `node` right away, before its value is read, so a write from
+/// another thread is either seen by the read or marks the computation again.
+pub(crate) fn track(node: Arc<dyn Node>) {
+    TRACKER.with_borrow_mut(|tracker| {
+        if let Some(tracker) = tracker
+            && !tracker.sources.iter().any(|source| same(source, &node))
+        {
+            node.core().add_observer(tracker.observer.clone());
+            tracker.sources.push(node);
+        }
+    });
+}
+
+/// Runs `f` without recording reads as dependencies.
+pub fn untrack<R>(f: impl FnOnce() -> R) -> R {
+    let _scope = TrackerScope::enter(None);
+    f()
+}
+
+/// Groups writes so effects run once, after `f` returns, instead of after every write.
+///
+/// Batches are per thread; nested batches flush when the outermost one ends.
+pub fn batch<R>(f: impl FnOnce() -> R) -> R {
+    let result = {
+        let _scope = BatchScope::enter();
+        f()
+    };
+    flush();
+    result
+}
+
+/// Marks everything downstream of a written signal and runs the affected effects.
+pub(crate) fn notify(signal: &dyn Node) {
+    let mut effects = Vec::new();
+    for observer in signal.core().observers() {
+        mark(&observer, NodeState::Dirty, &mut effects);
+    }
+    PENDING.with_borrow_mut(|pending| pending.extend(effects));
+    flush();
+}
+
+fn mark(node: &Arc<dyn Node>, state: NodeState, effects: &mut Vec<Arc<dyn Node>>) {
+    let core = node.core();
+    {
+        let mut links = core.links();
+        // A node being updated may already have read the old value; the thread updating it
+        // runs it again instead.
+        let updating = links.updating > 0;
+        if updating {
+            links.remarked = links.remarked.max(Some(state));
+        }
+        if links.state >= state && !updating {
+            return;
+        }
+        if links.state == NodeState::Clean && core.effect && !updating {
+            effects.push(Arc::clone(node));
+        }
+        links.state = links.state.max(state);
+    }
+    for observer in core.observers() {
+        mark(&observer, NodeState::Check, effects);
+    }
+}
+
+/// Keeps a node marked as updating; a panic leaves it clean so the next change runs it again.
+struct UpdateScope<'a> {
+    node: &'a Arc<dyn Node>,
+    done: bool,
+}
+
+impl<'a> UpdateScope<'a> {
+    fn enter(node: &'a Arc<dyn Node>) -> Self {
+        node.core().links().updating += 1;
+        Self { node, done: false }
+    }
+
+    /// Leaves the node clean, unless it was marked again while updating. Returns whether it
+    /// is up to date.
+    fn finish(&mut self) -> bool {
+        let mut links = self.node.core().links();
+        let remarked = links.remarked.take();
+        links.state = remarked.unwrap_or(NodeState::Clean);
+        self.done = remarked.is_none();
+        self.done
+    }
+}
+
+impl Drop for UpdateScope<'_> {
+    fn drop(&mut self) {
+        let mut links = self.node.core().links();
+        links.updating -= 1;
+        if !self.done {
+            links.state = NodeState::Clean;
+            links.remarked = None;
+        }
+    }
+}
+
+/// Brings `node` up to date, recomputing only when one of its sources really changed.
+///
+/// Writes from other threads during the update mark the node again, and it is updated once
+/// more, so it never ends up clean with a value computed from outdated sources.
+pub(crate) fn update_if_necessary(node: &Arc<dyn Node>) {
+    let mut scope = UpdateScope::enter(node);
+    loop {
+        let (state, sources) = {
+            let links = node.core().links();
+            if links.disposed {
+                return;
+            }
+            (links.state, links.sources.clone())
+        };
+
+        if state == NodeState::Check {
+            for source in &sources {
+                update_if_necessary(source);
+                if node.core().links().state == NodeState::Dirty {
+                    break;
+                }
+            }
+        }
+
+        if node.core().links().state == NodeState::Dirty {
+            recompute(node);
+        }
+        if scope.finish() {
+            return;
+        }
+    }
+}
+
+/// Keeps the sources of a node whose computation panicked, so the panic does not cut it
+/// off the graph.
+///
+/// The node keeps observing its previous sources as well as the ones it read before
+/// panicking, so the next change of any of them runs it again.
+struct RecomputeGuard<'a> {
+    node: &'a Arc<dyn Node>,
+    old_sources: Option<Vec<Arc<dyn Node>>>,
+}
+
+impl RecomputeGuard<'_> {
+    /// The computation returned normally.
+    fn disarm(mut self) {
+        self.old_sources = None;
+    }
+}
+
+impl Drop for RecomputeGuard<'_> {
+    fn drop(&mut self) {
+        let Some(mut sources) = self.old_sources.take() else {
+            return;
+        };
+        let read = TRACKER.with_borrow(|tracker| {
+            tracker
+                .as_ref()
+                .map(|tracker| tracker.sources.clone())
+                .unwrap_or_default()
+        });
+        for source in read {
+            if !sources.iter().any(|known| same(known, &source)) {
+                sources.push(source);
+            }
+        }
+        self.node.core().links().sources = sources;
+    }
+}
+
+fn recompute(node: &Arc<dyn Node>) {
+    let weak = Arc::downgrade(node);
+    // The old sources stay observed while computing, so their writes are not missed.
+    let old_sources = node.core().links().sources.clone();
+
+    let scope = TrackerScope::enter(Some(Tracker {
+        observer: weak.clone(),
+        sources: Vec::new(),
+    }));
+    // Dropped before the scope, while the sources read so far are still recorded.
+    let guard = RecomputeGuard {
+        node,
+        old_sources: Some(old_sources.clone()),
+    };
+    let changed = node.compute();
+    guard.disarm();
+    let sources = scope.exit();
+
+    for source in &old_sources {
+        if !sources.iter().any(|read| same(read, source)) {
+            source.core().remove_observer(&weak);
+        }
+    }
+    node.core().links().sources = sources;
+
+    if changed {
+        for observer in node.core().observers() {
+            observer.core().links().state = NodeState::Dirty;
+        }
+    }
+}
+
+/// Detaches a node from the graph; pending runs of a disposed effect are skipped.
+pub(crate) fn dispose(node: &Arc<dyn Node>) {
+    let weak = Arc::downgrade(node);
+    let sources = {
+        let mut links = node.core().links();
+        links.disposed = true;
+        std::mem::take(&mut links.sources)
+    };
+    for source in &sources {
+        source.core().remove_observer(&weak);
+    }
+}
+
+fn flush() {
+    if BATCH_DEPTH.get() > 0 || FLUSHING.get() {
+        return;
+    }
+
+    let mut panic = None;
+    {
+        let _scope = FlushScope::enter();
+        loop {
+            let effects = PENDING.take();
+            if effects.is_empty() {
+                break;
+            }
+            for effect in &effects {
+                // A panicking effect must not keep the rest of the batch from running; the
+                // first panic is raised again once everything ran.
+                let result =
+                    std::panic::catch_unwind(AssertUnwindSafe(|| update_if_necessary(effect)));
+                if let Err(payload) = result {
+                    panic.get_or_insert(payload);
+                }
+            }
+        }
+    }
+    if let Some(payload) = panic {
+        std::panic::resume_unwind(payload);
+    }
+}
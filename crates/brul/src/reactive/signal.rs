@@ -0,0 +1,119 @@
+use super::runtime::{self, Node, NodeCore, NodeState};
+use std::{
+    fmt,
+    sync::{Arc, RwLock},
+};
+
+struct SignalInner<T> {
+    core: NodeCore,
+    value: RwLock<T>,
+}
+
+impl<T> fmt::Debug for SignalInner<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Signal").finish_non_exhaustive()
+    }
+}
+
+impl<T: Send + Sync + 'static> Node for SignalInner<T> {
+    fn core(&self) -> &NodeCore {
+        &self.core
+    }
+
+    fn compute(&self) -> bool {
+        false
+    }
+}
+
+/// Writable reactive value. Clones share the same value.
+pub struct Signal<T> {
+    inner: Arc<SignalInner<T>>,
+}
+
+impl<T: Send + Sync + 'static> Signal<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            inner: Arc::new(SignalInner {
+                core: NodeCore::new(NodeState::Clean, false),
+                value: RwLock::new(value),
+            }),
+        }
+    }
+
+    /// Reads the value through `f`, tracking the signal as a dependency.
+    pub fn with<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        runtime::track(self.inner.clone());
+        f(&self
+            .inner
+            .value
+            .read()
+            .unwrap_or_else(|err| err.into_inner()))
+    }
+
+    pub fn get(&self) -> T
+    where
+        T: Clone,
+    {
+        self.with(T::clone)
+    }
+
+    /// Reads the value without tracking it.
+    pub fn get_untracked(&self) -> T
+    where
+        T: Clone,
+    {
+        runtime::untrack(|| self.get())
+    }
+
+    /// Replaces the value and notifies dependents, even if the value is equal.
+    pub fn set(&self, value: T) {
+        self.update(|current| *current = value);
+    }
+
+    /// Replaces the value only when it differs from the current one.
+    pub fn set_if_changed(&self, value: T)
+    where
+        T: PartialEq,
+    {
+        {
+            let mut current = self
+                .inner
+                .value
+                .write()
+                .unwrap_or_else(|err| err.into_inner());
+            if *current == value {
+                return;
+            }
+            *current = value;
+        }
+        runtime::notify(&*self.inner);
+    }
+
+    /// Mutates the value in place and notifies dependents.
+    pub fn update<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        let result = f(&mut self
+            .inner
+            .value
+            .write()
+            .unwrap_or_else(|err| err.into_inner()));
+        runtime::notify(&*self.inner);
+        result
+    }
+}
+
+impl<T> Clone for Signal<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Signal<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.inner.value.try_read() {
+            Ok(value) => f.debug_tuple("Signal").field(&*value).finish(),
+            Err(_) => f.debug_tuple("Signal").field(&"<locked>").finish(),
+        }
+    }
+}
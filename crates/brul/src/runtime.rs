@@ -1,66 +1,356 @@
-use brul_utils::AppControlMessage;
-use std::sync::mpsc::{self, Sender};
+use brul_utils::{Error, Result};
+use parking_lot::{Condvar, Mutex};
+use std::{
+    collections::HashMap,
+    panic::Location,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
 use tokio::{
-    runtime::{Handle, Runtime},
-    task::JoinHandle,
+    runtime::{Builder, Handle, Runtime},
+    sync::oneshot,
+    task::{AbortHandle, JoinHandle},
 };
+use tokio_util::sync::CancellationToken;
 
-pub(crate) struct RuntimeManager {
+/// How long background tasks get to stop after the app exits, unless configured.
+const DEFAULT_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// Tokio runtime the app runs commands, listeners and background tasks on.
+///
+/// ```no_run
+/// use brul::{AppBuilder, RuntimeConfig};
+///
+/// AppBuilder::new()
+///     .runtime(RuntimeConfig::multi_thread().worker_threads(2).thread_name("app-worker"))
+///     .run()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct RuntimeConfig {
+    kind: RuntimeKind,
+    worker_threads: Option<usize>,
+    thread_name: Option<String>,
+    thread_stack_size: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+enum RuntimeKind {
+    MultiThread,
+    CurrentThread,
+    Existing(Handle),
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self::multi_thread()
+    }
+}
+
+impl RuntimeConfig {
+    fn new(kind: RuntimeKind) -> Self {
+        Self {
+            kind,
+            worker_threads: None,
+            thread_name: None,
+            thread_stack_size: None,
+        }
+    }
+
+    /// Runtime with a pool of worker threads, the default.
+    pub fn multi_thread() -> Self {
+        Self::new(RuntimeKind::MultiThread)
+    }
+
+    /// Runtime running every task on a single background thread.
+    pub fn current_thread() -> Self {
+        Self::new(RuntimeKind::CurrentThread)
+    }
+
+    /// Runs on an existing runtime, for apps embedded in a larger tokio application.
+    ///
+    /// The runtime has to keep making progress while [`crate::App::run`] blocks the calling
+    /// thread, and it is not shut down when the app exits.
+    pub fn existing(handle: Handle) -> Self {
+        Self::new(RuntimeKind::Existing(handle))
+    }
+
+    /// Number of worker threads of a multi-thread runtime; tokio picks one per core by default.
+    pub fn worker_threads(mut self, worker_threads: usize) -> Self {
+        self.worker_threads = Some(worker_threads);
+        self
+    }
+
+    pub fn thread_name(mut self, thread_name: impl Into<String>) -> Self {
+        self.thread_name = Some(thread_name.into());
+        self
+    }
+
+    /// Stack size of the runtime threads, in bytes.
+    pub fn thread_stack_size(mut self, thread_stack_size: usize) -> Self {
+        self.thread_stack_size = Some(thread_stack_size);
+        self
+    }
+
+    fn build(&self) -> Result<Runtime> {
+        let mut builder = match self.kind {
+            RuntimeKind::MultiThread => Builder::new_multi_thread(),
+            RuntimeKind::CurrentThread => Builder::new_current_thread(),
+            RuntimeKind::Existing(_) => unreachable!("an existing runtime is not built"),
+        };
+        if let Some(worker_threads) = self.worker_threads {
+            builder.worker_threads(worker_threads);
+        }
+        if let Some(thread_name) = &self.thread_name {
+            builder.thread_name(thread_name);
+        }
+        if let Some(thread_stack_size) = self.thread_stack_size {
+            builder.thread_stack_size(thread_stack_size);
+        }
+        builder.enable_all().build().map_err(Error::RuntimeBuild)
+    }
+
+    /// Rejects settings tokio would panic on or that have no effect.
+    fn validate(&self) -> Result<()> {
+        match (&self.kind, self.worker_threads) {
+            (RuntimeKind::MultiThread, Some(0)) => Err(Error::InvalidRuntimeConfig(
+                "a multi-thread runtime needs at least one worker thread",
+            )),
+            (RuntimeKind::CurrentThread, Some(_)) => Err(Error::InvalidRuntimeConfig(
+                "a current-thread runtime has no worker threads",
+            )),
+            (RuntimeKind::Existing(_), _)
+                if self.worker_threads.is_some()
+                    || self.thread_name.is_some()
+                    || self.thread_stack_size.is_some() =>
+            {
+                Err(Error::InvalidRuntimeConfig(
+                    "an existing runtime keeps its own thread settings",
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// How the background tasks stopped when the app exited, returned by [`crate::App::run`].
+#[derive(Debug, Clone, Default)]
+pub struct ShutdownReport {
+    stuck_tasks: Vec<&'static Location<'static>>,
+}
+
+impl ShutdownReport {
+    /// Spawn locations of the tasks that did not stop within the grace period and were
+    /// aborted.
+    pub fn stuck_tasks(&self) -> &[&'static Location<'static>] {
+        &self.stuck_tasks
+    }
+
+    /// Whether every task stopped within the grace period.
+    pub fn is_clean(&self) -> bool {
+        self.stuck_tasks.is_empty()
+    }
+}
+
+/// Background tasks spawned through the app, tracked so shutdown can wait for them.
+#[derive(Clone)]
+pub(crate) struct Tasks {
     handle: Handle,
-    control_tx: Sender<AppControlMessage>,
+    inner: Arc<TasksInner>,
 }
 
-impl RuntimeManager {
-    pub(crate) fn new() -> Self {
-        let (handle_tx, handle_rx) = mpsc::channel::<Handle>();
-        let (control_tx, control_rx) = mpsc::channel::<AppControlMessage>();
-
-        let backgound_thread = std::thread::spawn(move || {
-            let runtime = Runtime::new().unwrap();
-            let handle = runtime.handle().clone();
-
-            handle_tx.send(handle).unwrap();
-
-            runtime.block_on(async {
-                while let Ok(message) = control_rx.recv() {
-                    match message {
-                        AppControlMessage::RequestShutdown => break,
-                        _ => {}
-                    }
-                }
-            })
-        });
+struct TasksInner {
+    running: Mutex<HashMap<u64, RunningTask>>,
+    next_id: AtomicU64,
+    stopped: Condvar,
+    shutdown: CancellationToken,
+}
 
-        let handle = handle_rx.recv().unwrap();
+struct RunningTask {
+    location: &'static Location<'static>,
+    abort: Option<AbortHandle>,
+}
+
+/// Removes its task from [`Tasks`] when the task finishes, panics or is dropped.
+struct TaskGuard {
+    id: u64,
+    inner: Arc<TasksInner>,
+}
+
+impl Drop for TaskGuard {
+    fn drop(&mut self) {
+        self.inner.running.lock().remove(&self.id);
+        self.inner.stopped.notify_all();
+    }
+}
 
-        Self { handle, control_tx }
+impl Tasks {
+    fn new(handle: Handle) -> Self {
+        Self {
+            handle,
+            inner: Arc::new(TasksInner {
+                running: Mutex::new(HashMap::new()),
+                next_id: AtomicU64::new(0),
+                stopped: Condvar::new(),
+                shutdown: CancellationToken::new(),
+            }),
+        }
     }
 
     pub(crate) fn handle(&self) -> &Handle {
         &self.handle
     }
 
-    pub(crate) fn spawn<F>(&self, spawn_fn: F) -> JoinHandle<()>
+    #[track_caller]
+    pub(crate) fn spawn<F>(&self, future: F) -> JoinHandle<()>
     where
         F: Future<Output = ()> + Send + 'static,
     {
-        self.handle.spawn(spawn_fn)
+        let id = self.inner.next_id.fetch_add(1, Ordering::Relaxed);
+        let task = RunningTask {
+            location: Location::caller(),
+            abort: None,
+        };
+        self.inner.running.lock().insert(id, task);
+        let guard = TaskGuard {
+            id,
+            inner: Arc::clone(&self.inner),
+        };
+        let join_handle = self.handle.spawn(async move {
+            let _guard = guard;
+            future.await
+        });
+        // The task may already be done and gone.
+        if let Some(task) = self.inner.running.lock().get_mut(&id) {
+            task.abort = Some(join_handle.abort_handle());
+        }
+        join_handle
     }
 
-    pub(crate) fn block_on<F>(&self, spawn_fn: F) -> ()
-    where
-        F: Future<Output = ()> + Send + 'static,
-    {
-        self.handle.block_on(spawn_fn)
+    /// Token cancelled when the app starts shutting down.
+    pub(crate) fn shutdown_token(&self) -> CancellationToken {
+        self.inner.shutdown.clone()
+    }
+
+    /// Cancels the shutdown token and blocks until `deadline` for the tasks to finish, then
+    /// aborts the remaining ones.
+    ///
+    /// Returns the spawn locations of the aborted tasks.
+    fn stop(&self, deadline: Instant) -> Vec<&'static Location<'static>> {
+        self.inner.shutdown.cancel();
+        let mut running = self.inner.running.lock();
+        while !running.is_empty() {
+            if self
+                .inner
+                .stopped
+                .wait_until(&mut running, deadline)
+                .timed_out()
+            {
+                break;
+            }
+        }
+        let stuck: Vec<_> = running
+            .values()
+            .map(|task| (task.location, task.abort.clone()))
+            .collect();
+        // Aborting may drop the task right away, and with it its guard.
+        drop(running);
+        stuck
+            .into_iter()
+            .map(|(location, abort)| {
+                if let Some(abort) = abort {
+                    abort.abort();
+                }
+                location
+            })
+            .collect()
+    }
+}
+
+/// Owns the tokio runtime, or adopts an existing one, and the tasks spawned on it.
+pub(crate) struct RuntimeManager {
+    tasks: Tasks,
+    grace_period: Duration,
+    /// Set for an owned runtime: tells its thread to shut it down.
+    shutdown_tx: Option<oneshot::Sender<Duration>>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl RuntimeManager {
+    pub(crate) fn new(config: RuntimeConfig) -> Result<Self> {
+        config.validate()?;
+        if let RuntimeKind::Existing(handle) = config.kind {
+            return Ok(Self {
+                tasks: Tasks::new(handle),
+                grace_period: DEFAULT_GRACE_PERIOD,
+                shutdown_tx: None,
+                thread: None,
+            });
+        }
+
+        let runtime = config.build()?;
+        let tasks = Tasks::new(runtime.handle().clone());
+        let (shutdown_tx, shutdown_rx) = oneshot::channel::<Duration>();
+
+        // A current-thread runtime runs its tasks on this thread.
+        let mut thread = thread::Builder::new()
+            .name(config.thread_name.unwrap_or_else(|| "brul-runtime".into()));
+        if let Some(stack_size) = config.thread_stack_size {
+            thread = thread.stack_size(stack_size);
+        }
+        let thread = thread.spawn(move || {
+            // Dropped without a shutdown, nobody waits for the tasks.
+            let grace_period = runtime.block_on(async { shutdown_rx.await.unwrap_or_default() });
+            // Tasks stuck in blocking code can not be dropped, don't wait for them forever.
+            runtime.shutdown_timeout(grace_period);
+        })?;
+
+        Ok(Self {
+            tasks,
+            grace_period: DEFAULT_GRACE_PERIOD,
+            shutdown_tx: Some(shutdown_tx),
+            thread: Some(thread),
+        })
     }
 
-    pub(crate) fn shutdown(&self) {
-        self.control_tx
-            .send(AppControlMessage::RequestShutdown)
-            .unwrap();
+    pub(crate) fn with_grace_period(mut self, grace_period: Duration) -> Self {
+        self.grace_period = grace_period;
+        self
     }
 
-    // pub(crate) fn send_control(&self, message: ControlMessage) {
-    //     self.control_tx.send(message).unwrap();
-    // }
+    pub(crate) fn handle(&self) -> &Handle {
+        self.tasks.handle()
+    }
+
+    pub(crate) fn tasks(&self) -> &Tasks {
+        &self.tasks
+    }
+
+    /// Cancels the background tasks, waits for them up to the grace period and shuts down
+    /// an owned runtime. Tasks that did not stop in time are aborted and reported.
+    pub(crate) fn shutdown(&mut self) -> ShutdownReport {
+        let deadline = Instant::now() + self.grace_period;
+        let stuck_tasks = self.tasks.stop(deadline);
+        for location in &stuck_tasks {
+            tracing::warn!(
+                "Task spawned at {location} did not stop within {:?}, aborting it",
+                self.grace_period
+            );
+        }
+
+        // The grace period is shared with the tasks of the runtime itself.
+        if let Some(shutdown_tx) = self.shutdown_tx.take() {
+            let _ = shutdown_tx.send(deadline.saturating_duration_since(Instant::now()));
+        }
+        let joined = self.thread.take().map(thread::JoinHandle::join);
+        if matches!(joined, Some(Err(_))) {
+            tracing::error!("Runtime thread panicked during shutdown");
+        }
+
+        ShutdownReport { stuck_tasks }
+    }
 }
@@ -0,0 +1,433 @@
+//! Keyboard shortcuts bound to commands or callbacks.
+//!
+//! A shortcut is written as chords separated by spaces, each chord being modifiers and a key
+//! joined with `+`: `"Ctrl+Shift+P"`, `"Primary+S"` or the two-stroke `"Ctrl+K Ctrl+S"`.
+//! `Primary` is Command on macOS and Control elsewhere.
+
+use crate::{
+    AppHandle, AppManager,
+    command::Invoke,
+    input::{InputEvent, Key, KeyEvent, Modifiers, NamedKey, us_layout_character},
+};
+use brul_utils::{Error, Result, WindowId};
+use parking_lot::Mutex;
+use serde_json::Value;
+use std::{collections::HashMap, fmt, str::FromStr, sync::Arc};
+
+/// One key press together with the modifiers held down.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    pub key: Key,
+    pub modifiers: Modifiers,
+}
+
+impl KeyChord {
+    fn matches(&self, event: &KeyEvent) -> bool {
+        if self.modifiers != event.modifiers {
+            return false;
+        }
+        if self.key == normalize(&event.key) {
+            return true;
+        }
+        // Shift turns digits and symbols into other symbols ("Shift+1" types "!"), so those
+        // fall back to the key position. Letters follow the layout: on AZERTY "Ctrl+A" is the
+        // key that types "a", not the one in the US position of A.
+        let shifted_symbol = event.modifiers.shift
+            && matches!(&event.key, Key::Character(typed) if !typed.chars().any(char::is_alphanumeric));
+        if !shifted_symbol {
+            return false;
+        }
+        match (&self.key, event.physical_key.and_then(us_layout_character)) {
+            (Key::Character(c), Some(position)) => c.chars().eq([position]),
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for KeyChord {
+    type Err = Error;
+
+    fn from_str(chord: &str) -> Result<Self> {
+        let invalid = || Error::InvalidShortcut(chord.to_owned());
+        let mut modifiers = Modifiers::default();
+        let mut key = None;
+        for part in chord.split('+').map(str::trim) {
+            match part.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => modifiers.control = true,
+                "shift" => modifiers.shift = true,
+                "alt" | "option" => modifiers.alt = true,
+                "meta" | "super" | "cmd" | "command" => modifiers.meta = true,
+                "primary" | "cmdorctrl" => {
+                    if cfg!(target_os = "macos") {
+                        modifiers.meta = true;
+                    } else {
+                        modifiers.control = true;
+                    }
+                }
+                name if key.is_none() => key = Some(parse_key(name).ok_or_else(invalid)?),
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(Self {
+            key: key.ok_or_else(invalid)?,
+            modifiers,
+        })
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Modifiers {
+            shift,
+            control,
+            alt,
+            meta,
+        } = self.modifiers;
+        for (held, name) in [
+            (control, "Ctrl"),
+            (alt, "Alt"),
+            (shift, "Shift"),
+            (meta, "Meta"),
+        ] {
+            if held {
+                write!(f, "{name}+")?;
+            }
+        }
+        match &self.key {
+            Key::Character(c) => write!(f, "{}", c.to_uppercase()),
+            Key::Named(named) => write!(f, "{named:?}"),
+            Key::Dead(Some(c)) => write!(f, "{}", c.to_uppercase()),
+            Key::Dead(None) | Key::Unidentified => write!(f, "?"),
+        }
+    }
+}
+
+/// Sequence of chords pressed one after another.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Shortcut(Vec<KeyChord>);
+
+impl Shortcut {
+    pub fn chords(&self) -> &[KeyChord] {
+        &self.0
+    }
+
+    /// Whether one shortcut is the other or starts it, so both can not be told apart.
+    fn overlaps(&self, other: &Shortcut) -> bool {
+        self.0.iter().zip(&other.0).all(|(a, b)| a == b)
+    }
+}
+
+impl FromStr for Shortcut {
+    type Err = Error;
+
+    fn from_str(shortcut: &str) -> Result<Self> {
+        let chords = shortcut
+            .split_whitespace()
+            .map(KeyChord::from_str)
+            .collect::<Result<Vec<_>>>()?;
+        if chords.is_empty() {
+            return Err(Error::InvalidShortcut(shortcut.to_owned()));
+        }
+        Ok(Self(chords))
+    }
+}
+
+impl fmt::Display for Shortcut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, chord) in self.0.iter().enumerate() {
+            if index > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{chord}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Where a shortcut is active. Window shortcuts take precedence over global ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShortcutScope {
+    Global,
+    Window(WindowId),
+}
+
+impl From<WindowId> for ShortcutScope {
+    fn from(window: WindowId) -> Self {
+        ShortcutScope::Window(window)
+    }
+}
+
+type ShortcutCallback = dyn Fn(&AppHandle, WindowId) + Send + Sync + 'static;
+
+/// What a shortcut does when it is pressed.
+#[derive(Clone)]
+pub enum ShortcutAction {
+    /// Invokes a registered command with a JSON payload, on behalf of the window the
+    /// shortcut was pressed in: closing that window cancels the command.
+    Command { name: String, payload: Value },
+    /// Calls a function with the window the shortcut was pressed in.
+    Callback(Arc<ShortcutCallback>),
+}
+
+impl ShortcutAction {
+    pub fn command(name: impl Into<String>) -> Self {
+        Self::command_with(name, Value::Null)
+    }
+
+    pub fn command_with(name: impl Into<String>, payload: Value) -> Self {
+        ShortcutAction::Command {
+            name: name.into(),
+            payload,
+        }
+    }
+
+    pub fn callback<F>(callback: F) -> Self
+    where
+        F: Fn(&AppHandle, WindowId) + Send + Sync + 'static,
+    {
+        ShortcutAction::Callback(Arc::new(callback))
+    }
+
+    fn run(&self, app: &AppHandle, window: WindowId) {
+        match self {
+            ShortcutAction::Command { name, payload } => {
+                let invoke =
+                    Invoke::new(app.clone(), name.clone(), payload.clone()).with_window(window);
+                let handle = app.clone();
+                app.spawn(async move {
+                    let name = invoke.command().to_owned();
+                    if let Err(err) = handle.dispatch(invoke).await {
+                        tracing::error!("Shortcut command failed: {}", err.into_error(&name));
+                    }
+                });
+            }
+            ShortcutAction::Callback(callback) => callback(app, window),
+        }
+    }
+}
+
+impl fmt::Debug for ShortcutAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShortcutAction::Command { name, payload } => f
+                .debug_struct("Command")
+                .field("name", name)
+                .field("payload", payload)
+                .finish(),
+            ShortcutAction::Callback(_) => f.write_str("Callback"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ShortcutId(u64);
+
+#[derive(Debug)]
+struct Binding {
+    id: ShortcutId,
+    shortcut: Shortcut,
+    scope: ShortcutScope,
+    action: ShortcutAction,
+}
+
+#[derive(Debug, Default)]
+struct Registry {
+    bindings: Vec<Binding>,
+    /// Chords pressed so far in each window while a multi-stroke shortcut is in progress.
+    pending: HashMap<WindowId, Vec<KeyEvent>>,
+    next_id: u64,
+}
+
+/// Shortcut bindings of an app, matched against the key presses of its windows.
+#[derive(Debug, Default)]
+pub struct ShortcutRegistry {
+    inner: Mutex<Registry>,
+}
+
+impl ShortcutRegistry {
+    /// Binds `shortcut` in `scope`.
+    ///
+    /// Fails with [`Error::ShortcutConflict`] when the scope already has the same shortcut or
+    /// one that starts with it, like `Ctrl+K` and `Ctrl+K Ctrl+S`.
+    pub fn register(
+        &self,
+        shortcut: &str,
+        scope: impl Into<ShortcutScope>,
+        action: ShortcutAction,
+    ) -> Result<ShortcutId> {
+        let shortcut: Shortcut = shortcut.parse()?;
+        let scope = scope.into();
+        let mut registry = self.inner.lock();
+        if let Some(existing) = registry
+            .bindings
+            .iter()
+            .find(|binding| binding.scope == scope && binding.shortcut.overlaps(&shortcut))
+        {
+            return Err(Error::ShortcutConflict {
+                shortcut: shortcut.to_string(),
+                existing: existing.shortcut.to_string(),
+            });
+        }
+
+        registry.next_id += 1;
+        let id = ShortcutId(registry.next_id);
+        registry.bindings.push(Binding {
+            id,
+            shortcut,
+            scope,
+            action,
+        });
+        Ok(id)
+    }
+
+    pub fn unregister(&self, id: ShortcutId) -> bool {
+        let mut registry = self.inner.lock();
+        let before = registry.bindings.len();
+        registry.bindings.retain(|binding| binding.id != id);
+        registry.bindings.len() != before
+    }
+
+    /// Shortcuts active in `window`, window bindings first.
+    pub fn shortcuts(&self, window: WindowId) -> Vec<(ShortcutId, Shortcut)> {
+        let registry = self.inner.lock();
+        [ShortcutScope::Window(window), ShortcutScope::Global]
+            .iter()
+            .flat_map(|scope| {
+                registry
+                    .bindings
+                    .iter()
+                    .filter(move |binding| binding.scope == *scope)
+            })
+            .map(|binding| (binding.id, binding.shortcut.clone()))
+            .collect()
+    }
+
+    /// Drops the bindings and pending chords of a closed window.
+    pub(crate) fn remove_window(&self, window: WindowId) {
+        let mut registry = self.inner.lock();
+        registry
+            .bindings
+            .retain(|binding| binding.scope != ShortcutScope::Window(window));
+        registry.pending.remove(&window);
+    }
+
+    /// Feeds an input event of `window`; runs the action of a completed shortcut.
+    ///
+    /// The app calls this for every [`crate::input::Input`]; tests can feed keys directly.
+    pub fn handle_input(&self, app: &AppHandle, window: WindowId, event: &InputEvent) {
+        let InputEvent::KeyDown(key) = event else {
+            return;
+        };
+        if key.repeat || is_modifier(&key.key) {
+            return;
+        }
+
+        let action = {
+            let mut registry = self.inner.lock();
+            let mut pending = registry.pending.remove(&window).unwrap_or_default();
+            pending.push(key.clone());
+            let mut action = registry.find(window, &pending);
+            if action.is_none() && pending.len() > 1 {
+                // The sequence broke off, the last press may start a new one.
+                pending = vec![key.clone()];
+                action = registry.find(window, &pending);
+            }
+            match action {
+                Some(Match::Complete(action)) => Some(action),
+                Some(Match::Partial) => {
+                    registry.pending.insert(window, pending);
+                    None
+                }
+                None => None,
+            }
+        };
+
+        if let Some(action) = action {
+            action.run(app, window);
+        }
+    }
+}
+
+enum Match {
+    Complete(ShortcutAction),
+    Partial,
+}
+
+impl Registry {
+    fn find(&self, window: WindowId, pressed: &[KeyEvent]) -> Option<Match> {
+        let scopes = [ShortcutScope::Window(window), ShortcutScope::Global];
+        let mut partial = false;
+        for scope in scopes {
+            for binding in self
+                .bindings
+                .iter()
+                .filter(|binding| binding.scope == scope)
+            {
+                let chords = binding.shortcut.chords();
+                if chords.len() < pressed.len()
+                    || !chords
+                        .iter()
+                        .zip(pressed)
+                        .all(|(chord, key)| chord.matches(key))
+                {
+                    continue;
+                }
+                if chords.len() == pressed.len() {
+                    return Some(Match::Complete(binding.action.clone()));
+                }
+                partial = true;
+            }
+        }
+        partial.then_some(Match::Partial)
+    }
+}
+
+fn normalize(key: &Key) -> Key {
+    match key {
+        Key::Character(c) => Key::Character(c.to_lowercase()),
+        key => key.clone(),
+    }
+}
+
+fn is_modifier(key: &Key) -> bool {
+    matches!(
+        key,
+        Key::Named(NamedKey::Shift | NamedKey::Control | NamedKey::Alt | NamedKey::Super)
+    )
+}
+
+fn parse_key(name: &str) -> Option<Key> {
+    let named = match name {
+        "esc" | "escape" => NamedKey::Escape,
+        "enter" | "return" => NamedKey::Enter,
+        "tab" => NamedKey::Tab,
+        "space" => NamedKey::Space,
+        "backspace" => NamedKey::Backspace,
+        "delete" | "del" => NamedKey::Delete,
+        "insert" => NamedKey::Insert,
+        "home" => NamedKey::Home,
+        "end" => NamedKey::End,
+        "pageup" => NamedKey::PageUp,
+        "pagedown" => NamedKey::PageDown,
+        "up" | "arrowup" => NamedKey::ArrowUp,
+        "down" | "arrowdown" => NamedKey::ArrowDown,
+        "left" | "arrowleft" => NamedKey::ArrowLeft,
+        "right" | "arrowright" => NamedKey::ArrowRight,
+        "f1" => NamedKey::F1,
+        "f2" => NamedKey::F2,
+        "f3" => NamedKey::F3,
+        "f4" => NamedKey::F4,
+        "f5" => NamedKey::F5,
+        "f6" => NamedKey::F6,
+        "f7" => NamedKey::F7,
+        "f8" => NamedKey::F8,
+        "f9" => NamedKey::F9,
+        "f10" => NamedKey::F10,
+        "f11" => NamedKey::F11,
+        "f12" => NamedKey::F12,
+        "plus" => return Some(Key::Character("+".to_owned())),
+        name if name.chars().count() == 1 => return Some(Key::Character(name.to_owned())),
+        _ => return None,
+    };
+    Some(Key::Named(named))
+}
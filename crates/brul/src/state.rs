@@ -1,90 +1,256 @@
+use crate::{
+    app::{Event, EventBus},
+    reactive::{Memo, Signal},
+};
+use parking_lot::{ArcRwLockWriteGuard, Mutex, RawRwLock, RwLock};
 use std::{
     any::{Any, TypeId},
     collections::HashMap,
-    ops::Deref,
-    pin::Pin,
-    sync::Mutex,
+    ops::{Deref, DerefMut},
+    sync::Arc,
 };
 
-pub struct State<'s, T: Any + Send + Sync + 'static>(&'s T);
+/// Owned handle to managed state.
+///
+/// A handle is a snapshot: it keeps the value it was created with, even if the state is
+/// updated, replaced or unmanaged later. Ask the app for a new handle to see changes, or
+/// read [`crate::AppManager::watch_state`] to be told about them.
+///
+/// Keeping a handle alive is not free: the next write through [`StateMut`] has to clone
+/// the whole value so the handle can keep its snapshot.
+pub struct State<T: Send + Sync + 'static>(Arc<T>);
 
-impl<'s, T: Send + Sync + 'static> State<'s, T> {
+impl<T: Send + Sync + 'static> State<T> {
     pub fn inner(&self) -> &T {
+        &self.0
+    }
+
+    pub fn into_arc(self) -> Arc<T> {
         self.0
     }
 }
 
-impl<T: Send + Sync + 'static> Deref for State<'_, T> {
+impl<T: Send + Sync + 'static> Deref for State<T> {
     type Target = T;
 
     fn deref(&self) -> &T {
-        self.0
+        &self.0
     }
 }
 
-impl<T: Send + Sync + 'static> Clone for State<'_, T> {
+impl<T: Send + Sync + 'static> Clone for State<T> {
     fn clone(&self) -> Self {
-        State(self.0)
+        State(Arc::clone(&self.0))
     }
 }
 
-impl<T: Send + Sync + 'static + PartialEq> PartialEq for State<'_, T> {
+impl<T: Send + Sync + 'static + PartialEq> PartialEq for State<T> {
     fn eq(&self, other: &Self) -> bool {
         self.0 == other.0
     }
 }
 
-impl<T: Send + Sync + std::fmt::Debug> std::fmt::Debug for State<'_, T> {
+impl<T: Send + Sync + std::fmt::Debug> std::fmt::Debug for State<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_tuple("State").field(&self.0).finish()
     }
 }
 
-#[derive(Debug)]
+/// Exclusive write access to managed state.
+///
+/// Writes are copy-on-write: the first write clones the whole value if [`State`] handles
+/// still share it, and happens in place otherwise. Large state that is written often is
+/// better split into several managed types. Listeners are notified with
+/// [`Event::StateChanged`] when the guard is dropped, if it was written through.
+pub struct StateMut<T: Clone + Send + Sync + 'static> {
+    guard: Option<ArcRwLockWriteGuard<RawRwLock, Arc<T>>>,
+    cell: StateCell<T>,
+    event_bus: EventBus,
+    /// Set by the first mutable access; a guard only read from notifies nobody.
+    dirty: bool,
+}
+
+impl<T: Clone + Send + Sync + 'static> Deref for StateMut<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.guard.as_ref().expect("guard is only taken on drop")
+    }
+}
+
+impl<T: Clone + Send + Sync + 'static> DerefMut for StateMut<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.dirty = true;
+        Arc::make_mut(self.guard.as_mut().expect("guard is only taken on drop"))
+    }
+}
+
+impl<T: Clone + Send + Sync + 'static> Drop for StateMut<T> {
+    fn drop(&mut self) {
+        // Release the lock first so listeners can read the new value.
+        drop(self.guard.take());
+        if self.dirty {
+            self.cell.notify(&self.event_bus);
+        }
+    }
+}
+
+impl<T: Clone + Send + Sync + std::fmt::Debug> std::fmt::Debug for StateMut<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("StateMut").field(&**self).finish()
+    }
+}
+
+struct StateCell<T> {
+    value: Arc<RwLock<Arc<T>>>,
+    revision: Signal<u64>,
+}
+
+impl<T> Clone for StateCell<T> {
+    fn clone(&self) -> Self {
+        Self {
+            value: Arc::clone(&self.value),
+            revision: self.revision.clone(),
+        }
+    }
+}
+
+impl<T: Send + Sync + 'static> StateCell<T> {
+    fn new(value: Arc<T>) -> Self {
+        Self {
+            value: Arc::new(RwLock::new(value)),
+            revision: Signal::new(0),
+        }
+    }
+
+    fn snapshot(&self) -> State<T> {
+        State(Arc::clone(&self.value.read()))
+    }
+
+    fn notify(&self, event_bus: &EventBus) {
+        self.revision.update(|revision| *revision += 1);
+        event_bus.emit(Event::StateChanged {
+            type_id: TypeId::of::<T>(),
+            type_name: std::any::type_name::<T>(),
+        });
+    }
+}
+
 pub struct StateManager {
-    map: Mutex<HashMap<TypeId, Pin<Box<dyn Any + Send + Sync>>>>,
+    map: Mutex<HashMap<TypeId, Box<dyn Any + Send + Sync>>>,
+    event_bus: EventBus,
+}
+
+impl std::fmt::Debug for StateManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StateManager")
+            .field("map", &self.map)
+            .finish_non_exhaustive()
+    }
 }
 
 impl StateManager {
-    pub(crate) fn new() -> Self {
+    pub(crate) fn new(event_bus: EventBus) -> Self {
         Self {
             map: Mutex::new(HashMap::new()),
+            event_bus,
         }
     }
 
-    pub(crate) fn set<T: Any + Send + Sync + 'static>(&self, state: T) -> bool {
-        let mut map = self.map.lock().unwrap();
+    pub(crate) fn set<T: Send + Sync + 'static>(&self, state: T) -> bool {
+        let mut map = self.map.lock();
 
         if map.contains_key(&TypeId::of::<T>()) {
             return false;
         }
 
-        let box_ptr = Box::pin(state) as Pin<Box<dyn Any + Send + Sync>>;
-
-        map.insert(TypeId::of::<T>(), box_ptr);
+        map.insert(TypeId::of::<T>(), Box::new(StateCell::new(Arc::new(state))));
 
         true
     }
 
-    pub fn get<T: Any + Send + Sync + 'static>(&self) -> State<'_, T> {
-        self.try_get()
+    fn try_cell<T: Send + Sync + 'static>(&self) -> Option<StateCell<T>> {
+        let map = self.map.lock();
+        map.get(&TypeId::of::<T>())?
+            .downcast_ref::<StateCell<T>>()
+            .cloned()
+    }
+
+    fn cell<T: Send + Sync + 'static>(&self) -> StateCell<T> {
+        self.try_cell()
             .unwrap_or_else(|| panic!("State not found for type {}", std::any::type_name::<T>()))
     }
 
-    pub fn try_get<T: Any + Send + Sync + 'static>(&self) -> Option<State<'_, T>> {
-        let map = self.map.lock().unwrap();
+    pub fn get<T: Send + Sync + 'static>(&self) -> State<T> {
+        self.cell::<T>().snapshot()
+    }
+
+    pub fn try_get<T: Send + Sync + 'static>(&self) -> Option<State<T>> {
+        Some(self.try_cell::<T>()?.snapshot())
+    }
+
+    pub fn get_mut<T: Clone + Send + Sync + 'static>(&self) -> StateMut<T> {
+        self.try_get_mut()
+            .unwrap_or_else(|| panic!("State not found for type {}", std::any::type_name::<T>()))
+    }
 
-        let type_id = TypeId::of::<T>();
+    pub fn try_get_mut<T: Clone + Send + Sync + 'static>(&self) -> Option<StateMut<T>> {
+        let cell = self.try_cell::<T>()?;
+        Some(StateMut {
+            guard: Some(cell.value.write_arc()),
+            cell,
+            event_bus: self.event_bus.clone(),
+            dirty: false,
+        })
+    }
 
-        let box_ptr = map.get(&type_id)?;
+    /// Mutates the state of type `T` and notifies listeners afterwards.
+    pub fn update<T: Clone + Send + Sync + 'static, R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        f(&mut self.get_mut::<T>())
+    }
 
-        let v_ref = unsafe {
-            // SAFETY: We know that the type_id is the same as the type of T, so we can safely downcast it.
-            let value = box_ptr.downcast_ref::<T>().unwrap_unchecked();
-            // SAFETY: We know that the value is a valid pointer to T, so we can safely create a State from it.
-            &*(value as *const T)
+    /// Swaps in a new value, or manages it if `T` was not managed yet.
+    ///
+    /// Returns the previous value; existing [`State`] handles keep seeing it.
+    pub fn replace<T: Send + Sync + 'static>(&self, state: T) -> Option<State<T>> {
+        let state = Arc::new(state);
+        let cell = {
+            let mut map = self.map.lock();
+            match map
+                .get(&TypeId::of::<T>())
+                .and_then(|cell| cell.downcast_ref::<StateCell<T>>())
+            {
+                Some(cell) => cell.clone(),
+                None => {
+                    map.insert(TypeId::of::<T>(), Box::new(StateCell::new(state)));
+                    return None;
+                }
+            }
         };
 
-        Some(State(v_ref))
+        let previous = std::mem::replace(&mut *cell.value.write(), state);
+        cell.notify(&self.event_bus);
+        Some(State(previous))
+    }
+
+    /// Stops managing `T` and returns its last value.
+    pub fn remove<T: Send + Sync + 'static>(&self) -> Option<State<T>> {
+        let cell = self
+            .map
+            .lock()
+            .remove(&TypeId::of::<T>())?
+            .downcast::<StateCell<T>>()
+            .ok()?;
+        let last = cell.snapshot();
+        cell.notify(&self.event_bus);
+        Some(last)
+    }
+
+    /// Revision of the state of type `T`, increased by every update.
+    ///
+    /// Reading it inside a [`crate::reactive::Effect`] or [`Memo`] re-runs them on change.
+    pub fn watch<T: Send + Sync + 'static>(&self) -> Memo<u64> {
+        let revision = self.cell::<T>().revision;
+        Memo::new(move || revision.get())
     }
 }
@@ -1,2 +1,262 @@
+use crate::{AppHandle, command::Invoke};
+use brul_utils::{
+    Color, CursorIcon, Fullscreen, Point, Result, Size, WindowConfig, WindowId, WindowSize,
+    WindowUpdate,
+    input::{InputEvent, PointerState},
+};
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    panic::{RefUnwindSafe, UnwindSafe},
+    sync::RwLock,
+};
+use tokio_util::sync::CancellationToken;
+
+#[derive(Debug)]
+struct WindowState {
+    config: WindowConfig,
+    size: Option<WindowSize>,
+    pointer: PointerState,
+    /// Parent of the tokens of commands invoked for this window.
+    commands: CancellationToken,
+}
+
+/// App side registry of open windows, kept in sync with the GUI thread.
 #[derive(Default)]
-pub struct WindowManager {}
+pub struct WindowManager {
+    windows: RwLock<HashMap<WindowId, WindowState>>,
+}
+
+impl WindowManager {
+    pub(crate) fn insert(&self, id: WindowId, config: WindowConfig) {
+        self.windows
+            .write()
+            .unwrap()
+            .entry(id)
+            .or_insert(WindowState {
+                config,
+                size: None,
+                pointer: PointerState::default(),
+                commands: CancellationToken::new(),
+            });
+    }
+
+    /// Forgets the window and cancels the commands still running for it.
+    pub(crate) fn remove(&self, id: WindowId) {
+        if let Some(window) = self.windows.write().unwrap().remove(&id) {
+            window.commands.cancel();
+        }
+    }
+
+    /// Token for a command invoked for window `id`, `None` if the window is gone.
+    pub(crate) fn command_token(&self, id: WindowId) -> Option<CancellationToken> {
+        Some(
+            self.windows
+                .read()
+                .unwrap()
+                .get(&id)?
+                .commands
+                .child_token(),
+        )
+    }
+
+    pub(crate) fn set_size(&self, id: WindowId, size: WindowSize) {
+        if let Some(window) = self.windows.write().unwrap().get_mut(&id) {
+            window.size = Some(size);
+        }
+    }
+
+    pub(crate) fn input(&self, id: WindowId, event: &InputEvent) {
+        if let Some(window) = self.windows.write().unwrap().get_mut(&id) {
+            window.pointer.update(event);
+        }
+    }
+
+    pub(crate) fn apply(&self, id: WindowId, update: &WindowUpdate) {
+        if let Some(window) = self.windows.write().unwrap().get_mut(&id) {
+            window.config.apply(update);
+        }
+    }
+
+    pub fn contains(&self, id: WindowId) -> bool {
+        self.windows.read().unwrap().contains_key(&id)
+    }
+
+    pub fn ids(&self) -> Vec<WindowId> {
+        self.windows.read().unwrap().keys().copied().collect()
+    }
+
+    /// Config the window was created with, including later runtime changes.
+    pub fn config(&self, id: WindowId) -> Option<WindowConfig> {
+        Some(self.windows.read().unwrap().get(&id)?.config.clone())
+    }
+
+    /// Last known size of a window, `None` until the GUI reports it.
+    pub fn size(&self, id: WindowId) -> Option<WindowSize> {
+        self.windows.read().unwrap().get(&id)?.size
+    }
+
+    /// Pointer state of a window, as of the last input event the app handled.
+    pub fn pointer(&self, id: WindowId) -> Option<PointerState> {
+        Some(self.windows.read().unwrap().get(&id)?.pointer)
+    }
+
+    /// Window the pointer is over, if any.
+    pub fn hovered(&self) -> Option<WindowId> {
+        self.windows
+            .read()
+            .unwrap()
+            .iter()
+            .find(|(_, window)| window.pointer.hovered)
+            .map(|(id, _)| *id)
+    }
+}
+
+/// Cheap, cloneable reference to a window owned by the GUI thread.
+#[derive(Clone)]
+pub struct WindowHandle {
+    id: WindowId,
+    app: AppHandle,
+}
+
+impl WindowHandle {
+    pub(crate) fn new(id: WindowId, app: AppHandle) -> Self {
+        Self { id, app }
+    }
+
+    pub fn id(&self) -> WindowId {
+        self.id
+    }
+
+    /// Emits `event` to the listeners of this window, see [`crate::EventBus::emit_to`].
+    pub fn emit<E: Send + Sync + 'static>(&self, event: E) {
+        self.app.emit_to(self.id, event)
+    }
+
+    /// Listens to events emitted to this window or to all windows. The listener is dropped
+    /// when the window closes.
+    pub fn listen<E, F>(&self, callback: F) -> u64
+    where
+        E: Send + Sync + 'static,
+        F: Fn(&E) + UnwindSafe + RefUnwindSafe + Send + Sync + 'static,
+    {
+        self.app.event_bus().listen_to(self.id, callback)
+    }
+
+    /// Last known size, both physical and logical via [`WindowSize`].
+    pub fn size(&self) -> Option<WindowSize> {
+        self.app.windows().size(self.id)
+    }
+
+    pub fn config(&self) -> Option<WindowConfig> {
+        self.app.windows().config(self.id)
+    }
+
+    pub fn pointer(&self) -> Option<PointerState> {
+        self.app.windows().pointer(self.id)
+    }
+
+    /// Invokes a command on behalf of this window; closing the window cancels it.
+    pub async fn invoke(&self, command: &str, payload: Value) -> Result<Value> {
+        let invoke = Invoke::new(self.app.clone(), command, payload).with_window(self.id);
+        self.app
+            .dispatch(invoke)
+            .await
+            .map_err(|err| err.into_error(command))
+    }
+
+    /// Closes the window, see [`AppHandle::close_window`].
+    pub fn close(&self) -> Result<()> {
+        self.app.close_window(self.id)
+    }
+
+    pub async fn set_title(&self, title: impl Into<String>) -> Result<()> {
+        self.app.set_title(self.id, title).await
+    }
+
+    pub async fn set_cursor(&self, cursor: CursorIcon) -> Result<()> {
+        self.app.set_cursor(self.id, cursor).await
+    }
+
+    pub fn set_cursor_visible(&self, visible: bool) -> Result<()> {
+        self.app.set_cursor_visible(self.id, visible)
+    }
+
+    /// See [`AppHandle::set_pointer_capture`].
+    pub fn set_pointer_capture(&self, captured: bool) -> Result<()> {
+        self.app.set_pointer_capture(self.id, captured)
+    }
+
+    pub async fn request_redraw(&self) -> Result<()> {
+        self.app.request_redraw(self.id).await
+    }
+
+    /// Queries the GUI thread for the current size and refreshes the cached one.
+    pub async fn inner_size(&self) -> Result<WindowSize> {
+        self.app.inner_size(self.id).await
+    }
+
+    /// Requests a new inner size in logical pixels; the platform may pick a different one.
+    pub fn set_inner_size(&self, size: Size) -> Result<()> {
+        self.update(WindowUpdate::InnerSize(size))
+    }
+
+    pub fn set_min_inner_size(&self, size: Option<Size>) -> Result<()> {
+        self.update(WindowUpdate::MinInnerSize(size))
+    }
+
+    pub fn set_max_inner_size(&self, size: Option<Size>) -> Result<()> {
+        self.update(WindowUpdate::MaxInnerSize(size))
+    }
+
+    pub fn set_position(&self, position: Point) -> Result<()> {
+        self.update(WindowUpdate::Position(position))
+    }
+
+    pub fn set_decorations(&self, decorations: bool) -> Result<()> {
+        self.update(WindowUpdate::Decorations(decorations))
+    }
+
+    pub fn set_resizable(&self, resizable: bool) -> Result<()> {
+        self.update(WindowUpdate::Resizable(resizable))
+    }
+
+    pub fn set_always_on_top(&self, always_on_top: bool) -> Result<()> {
+        self.update(WindowUpdate::AlwaysOnTop(always_on_top))
+    }
+
+    pub fn set_transparent(&self, transparent: bool) -> Result<()> {
+        self.update(WindowUpdate::Transparent(transparent))
+    }
+
+    pub fn set_fullscreen(&self, fullscreen: Option<Fullscreen>) -> Result<()> {
+        self.update(WindowUpdate::Fullscreen(fullscreen))
+    }
+
+    /// Changes the clear color of the window and redraws it.
+    pub fn set_background_color(&self, color: Color) -> Result<()> {
+        self.update(WindowUpdate::BackgroundColor(color))
+    }
+
+    /// Lets the platform input method compose text in the window. Composition is reported
+    /// as [`InputEvent::Ime`](crate::input::InputEvent::Ime) input.
+    pub fn set_ime_allowed(&self, allowed: bool) -> Result<()> {
+        self.update(WindowUpdate::ImeAllowed(allowed))
+    }
+
+    /// Tells the input method where the text cursor is, in logical pixels, so the candidate
+    /// window does not cover it.
+    pub fn set_ime_cursor_area(&self, position: Point, size: Size) -> Result<()> {
+        self.update(WindowUpdate::ImeCursorArea { position, size })
+    }
+
+    fn update(&self, update: WindowUpdate) -> Result<()> {
+        self.app.update_window(self.id, update)
+    }
+}
+
+impl std::fmt::Debug for WindowHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("WindowHandle").field(&self.id).finish()
+    }
+}
@@ -0,0 +1,236 @@
+use brul::{
+    AppBuilder, AppHandle, AppManager, Error, State,
+    command::{Cancellation, CommandError, Invoke},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+struct Greeting(&'static str);
+
+#[brul::command]
+fn add(a: i32, b: i32) -> i32 {
+    a + b
+}
+
+#[brul::command]
+fn greet(greeting: State<Greeting>, name: Option<String>) -> String {
+    format!("{}, {}", greeting.0, name.as_deref().unwrap_or("world"))
+}
+
+#[brul::command]
+fn divide(a: i32, b: i32) -> Result<i32, String> {
+    a.checked_div(b)
+        .ok_or_else(|| "division by zero".to_string())
+}
+
+type Checked<T> = Result<T, String>;
+
+#[brul::command]
+async fn sqrt(value: f64) -> Checked<f64> {
+    if value < 0.0 {
+        return Err(format!("{value} has no real square root"));
+    }
+    Ok(value.sqrt())
+}
+
+#[brul::command]
+fn has_greeting(app: AppHandle) -> bool {
+    app.try_state::<Greeting>().is_some()
+}
+
+#[brul::command]
+async fn double_later(value: u32) -> u32 {
+    tokio::time::sleep(Duration::from_millis(5)).await;
+    value * 2
+}
+
+#[brul::command(timeout_ms = 50)]
+async fn sleep_forever(cancellation: Cancellation) -> bool {
+    tokio::time::sleep(Duration::from_secs(60)).await;
+    cancellation.is_cancelled()
+}
+
+#[derive(Deserialize)]
+struct Resize {
+    width: u32,
+    height: u32,
+}
+
+#[derive(Serialize)]
+struct Area {
+    pixels: u32,
+}
+
+#[brul::command]
+fn area(size: Resize) -> Area {
+    Area {
+        pixels: size.width * size.height,
+    }
+}
+
+mod nested {
+    #[brul::command]
+    pub fn ping() -> &'static str {
+        "pong"
+    }
+}
+
+fn app(with_state: bool) -> brul::App {
+    let builder = AppBuilder::new().invoke_handler(brul::generate_handlers![
+        add,
+        greet,
+        divide,
+        sqrt,
+        has_greeting,
+        double_later,
+        area,
+        sleep_forever,
+        nested::ping,
+    ]);
+    let builder = if with_state {
+        builder.manage(Greeting("Hello"))
+    } else {
+        builder
+    };
+    builder.build().unwrap()
+}
+
+#[tokio::test]
+async fn invokes_with_payload_and_state() {
+    let app = app(true);
+    let handle = app.app_handle();
+
+    assert_eq!(
+        handle.invoke("add", json!({"a": 2, "b": 3})).await.unwrap(),
+        json!(5)
+    );
+    assert_eq!(
+        handle
+            .invoke("greet", json!({"name": "brul"}))
+            .await
+            .unwrap(),
+        json!("Hello, brul")
+    );
+    assert_eq!(
+        handle.invoke("greet", json!({})).await.unwrap(),
+        json!("Hello, world")
+    );
+    assert_eq!(
+        handle.invoke("has_greeting", json!(null)).await.unwrap(),
+        json!(true)
+    );
+    assert_eq!(
+        handle.invoke("ping", json!(null)).await.unwrap(),
+        json!("pong")
+    );
+}
+
+#[tokio::test]
+async fn reports_typed_errors() {
+    let app = app(false);
+    let handle = app.app_handle();
+
+    assert!(matches!(
+        handle.invoke("missing", json!(null)).await,
+        Err(Error::UnknownCommand(name)) if name == "missing"
+    ));
+    assert!(matches!(
+        handle.invoke("greet", json!({})).await,
+        Err(Error::CommandFailed { command, message })
+            if command == "greet" && message.contains("Greeting")
+    ));
+    assert!(matches!(
+        handle.invoke("add", json!({"a": "two", "b": 3})).await,
+        Err(Error::InvalidCommandArgument { command, arg: "a", .. }) if command == "add"
+    ));
+    assert!(matches!(
+        handle.invoke("divide", json!({"a": 1, "b": 0})).await,
+        Err(Error::CommandFailed { message, .. }) if message == "division by zero"
+    ));
+    assert_eq!(
+        handle.invoke("sqrt", json!({"value": 9.0})).await.unwrap(),
+        json!(3.0)
+    );
+    assert!(matches!(
+        handle.invoke("sqrt", json!({"value": -1.0})).await,
+        Err(Error::CommandFailed { message, .. }) if message == "-1 has no real square root"
+    ));
+}
+
+#[tokio::test]
+async fn round_trips_serde_types() {
+    let app = app(false);
+
+    assert_eq!(
+        app.app_handle()
+            .invoke("area", json!({"size": {"width": 4, "height": 3}}))
+            .await
+            .unwrap(),
+        json!({"pixels": 12})
+    );
+}
+
+#[tokio::test]
+async fn runs_async_commands() {
+    let app = app(false);
+
+    assert_eq!(
+        app.app_handle()
+            .invoke("double_later", json!({"value": 21}))
+            .await
+            .unwrap(),
+        json!(42)
+    );
+}
+
+#[tokio::test]
+async fn times_out_and_cancels() {
+    let app = app(false);
+    let handle = app.app_handle().clone();
+
+    assert!(matches!(
+        handle.invoke("sleep_forever", json!(null)).await,
+        Err(Error::CommandTimedOut { timeout, .. }) if timeout == Duration::from_millis(50)
+    ));
+
+    let token = CancellationToken::new();
+    let invoke =
+        Invoke::new(handle.clone(), "sleep_forever", json!(null)).with_cancellation(token.clone());
+    let pending = tokio::spawn(async move { handle.dispatch(invoke).await });
+    token.cancel();
+    assert!(matches!(
+        pending.await.unwrap(),
+        Err(CommandError::Cancelled)
+    ));
+}
+
+#[brul::command(timeout_ms = 50)]
+fn block_for(ms: u64) -> u64 {
+    std::thread::sleep(Duration::from_millis(ms));
+    ms
+}
+
+#[tokio::test]
+async fn sync_commands_time_out_too() {
+    let app = AppBuilder::new()
+        .invoke_handler(brul::generate_handlers![block_for])
+        .build()
+        .unwrap();
+    let handle = app.app_handle().clone();
+
+    assert_eq!(
+        handle
+            .invoke("block_for", json!({ "ms": 1 }))
+            .await
+            .unwrap(),
+        1
+    );
+    let start = std::time::Instant::now();
+    assert!(matches!(
+        handle.invoke("block_for", json!({ "ms": 500 })).await,
+        Err(Error::CommandTimedOut { .. })
+    ));
+    assert!(start.elapsed() < Duration::from_millis(400));
+}
@@ -0,0 +1,191 @@
+use brul::{
+    AppBuilder, AppHandle, AppManager, Backpressure, Event, EventBus, EventDiscriminants,
+    EventTarget, util::WindowId,
+};
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+use tokio::sync::mpsc;
+
+struct Ping(usize);
+
+struct Pong;
+
+#[test]
+fn listeners_receive_their_event_type() {
+    let bus = EventBus::new();
+    let pings = Arc::new(AtomicUsize::new(0));
+    let id = bus.listen::<Ping, _>({
+        let pings = Arc::clone(&pings);
+        move |ping| {
+            pings.fetch_add(ping.0, Ordering::SeqCst);
+        }
+    });
+
+    bus.emit(Ping(2));
+    bus.emit(Pong);
+    bus.emit(Ping(3));
+    assert_eq!(pings.load(Ordering::SeqCst), 5);
+
+    bus.unlisten::<Ping>(id);
+    bus.emit(Ping(10));
+    assert_eq!(pings.load(Ordering::SeqCst), 5);
+}
+
+#[test]
+fn builtin_events_reach_typed_and_variant_subscribers() {
+    let bus = EventBus::new();
+    let typed = Arc::new(AtomicUsize::new(0));
+    let variant = Arc::new(AtomicUsize::new(0));
+    bus.listen::<Event, _>({
+        let typed = Arc::clone(&typed);
+        move |_| {
+            typed.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+    bus.subscribe(EventDiscriminants::AppShutdown, {
+        let variant = Arc::clone(&variant);
+        move |_| {
+            variant.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+
+    bus.emit(Event::AppStarted);
+    bus.emit(Event::AppShutdown);
+    assert_eq!(typed.load(Ordering::SeqCst), 2);
+    assert_eq!(variant.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn panicking_listener_does_not_stop_others() {
+    let bus = EventBus::new();
+    let calls = Arc::new(AtomicUsize::new(0));
+    bus.listen::<Pong, _>(|_| panic!("listener failed"));
+    bus.listen::<Pong, _>({
+        let calls = Arc::clone(&calls);
+        move |_| {
+            calls.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+
+    bus.emit(Pong);
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn builder_listeners_get_the_app_handle() {
+    #[derive(Debug, PartialEq)]
+    struct Received(usize);
+
+    let app = AppBuilder::new()
+        .add_listener(|app: &AppHandle, ping: &Ping| {
+            app.clone().manage(Received(ping.0));
+        })
+        .build()
+        .unwrap();
+
+    app.app_handle().emit(Ping(4));
+    assert_eq!(*app.state::<Received>(), Received(4));
+}
+
+#[test]
+fn once_listeners_fire_a_single_time() {
+    let bus = EventBus::new();
+    let calls = Arc::new(AtomicUsize::new(0));
+    bus.once::<Pong, _>({
+        let calls = Arc::clone(&calls);
+        move |_| {
+            calls.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+
+    bus.emit(Pong);
+    bus.emit(Pong);
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn async_listeners_run_on_the_runtime() {
+    let bus = EventBus::new();
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    bus.listen_async::<Ping, _, _>(move |ping| {
+        let tx = tx.clone();
+        async move {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            tx.send(ping.0).unwrap();
+        }
+    });
+
+    bus.emit(Ping(7));
+    assert_eq!(rx.recv().await, Some(7));
+}
+
+#[tokio::test]
+async fn streams_receive_emitted_events() {
+    let bus = EventBus::new();
+    let mut stream = bus.subscribe_stream::<Ping>();
+
+    let emitter = bus.clone();
+    tokio::spawn(async move {
+        for n in 0..3 {
+            emitter.emit(Ping(n));
+        }
+    });
+
+    for n in 0..3 {
+        assert_eq!(stream.recv().await.unwrap().0, n);
+    }
+    assert_eq!(stream.missed(), 0);
+}
+
+#[tokio::test]
+async fn slow_streams_follow_their_backpressure() {
+    let bus = EventBus::new().with_stream_capacity(2);
+    let mut dropping = bus.subscribe_stream::<Ping>();
+    let mut closing = bus.subscribe_stream_with::<Ping>(Backpressure::Close);
+
+    for n in 0..5 {
+        bus.emit(Ping(n));
+    }
+
+    assert_eq!(dropping.recv().await.unwrap().0, 3);
+    assert_eq!(dropping.recv().await.unwrap().0, 4);
+    assert_eq!(dropping.missed(), 3);
+    assert!(closing.recv().await.is_none());
+}
+
+#[test]
+fn targeted_events_skip_other_targets() {
+    let bus = EventBus::new();
+    let first = WindowId::next();
+    let second = WindowId::next();
+    let counts: [Arc<AtomicUsize>; 3] = Default::default();
+    let count = |index: usize| {
+        let counter = Arc::clone(&counts[index]);
+        move |ping: &Ping| {
+            counter.fetch_add(ping.0, Ordering::SeqCst);
+        }
+    };
+    bus.listen::<Ping, _>(count(0));
+    bus.listen_to::<Ping, _>(first, count(1));
+    bus.listen_to::<Ping, _>(second, count(2));
+
+    bus.emit_to(first, Ping(1));
+    bus.emit_to(EventTarget::Label("toolbar".into()), Ping(10));
+    // Reaches the global listener and the listeners of both targets.
+    bus.emit_all(Ping(100));
+
+    let load = || counts.each_ref().map(|count| count.load(Ordering::SeqCst));
+    assert_eq!(load(), [111, 101, 100]);
+
+    bus.unlisten_target(&EventTarget::Window(first));
+    bus.emit_to(first, Ping(1000));
+    assert_eq!(load(), [1111, 101, 100]);
+    // `emit` broadcasts like `emit_all`.
+    bus.emit(Ping(10000));
+    assert_eq!(load(), [11111, 101, 10100]);
+}
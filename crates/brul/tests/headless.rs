@@ -0,0 +1,78 @@
+use brul::{
+    AppBuilder, AppHandle, Event,
+    headless::SyntheticEvent,
+    input::{Input, InputEvent, Key, KeyCode},
+    util::WindowId,
+};
+use std::{
+    sync::mpsc,
+    time::{Duration, Instant},
+};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[test]
+fn run_headless_steps_frames_and_delivers_injected_input() {
+    let (events_tx, events_rx) = mpsc::channel();
+    let (keys_tx, keys_rx) = mpsc::channel();
+
+    let report = AppBuilder::new()
+        .add_listener(move |_: &AppHandle, event: &Event| {
+            if matches!(event, Event::Ready | Event::WindowClosed(_)) {
+                let _ = events_tx.send(format!("{event:?}"));
+            }
+        })
+        .add_listener(move |_: &AppHandle, input: &Input| {
+            if let InputEvent::KeyDown(key) = &input.event {
+                let _ = keys_tx.send((input.window, key.key.clone()));
+            }
+        })
+        .build()
+        .unwrap()
+        .run_headless(|backend| {
+            assert!(backend.windows().is_empty());
+            assert!(backend.step());
+            assert_eq!(backend.windows(), [WindowId::MAIN]);
+            assert_eq!(events_rx.recv_timeout(TIMEOUT).unwrap(), "Ready");
+
+            backend.inject(WindowId::MAIN, SyntheticEvent::KeyPressed(KeyCode::KeyA));
+            // Nothing is delivered before the next step.
+            assert!(keys_rx.recv_timeout(Duration::from_millis(50)).is_err());
+            assert!(backend.step());
+            assert_eq!(
+                keys_rx.recv_timeout(TIMEOUT).unwrap(),
+                (WindowId::MAIN, Key::Character("a".into()))
+            );
+            assert_eq!(backend.frame_count(), 2);
+        })
+        .unwrap();
+
+    assert!(report.is_clean());
+    // Returning from `drive` closes the windows that are still open.
+    assert_eq!(
+        events_rx.try_iter().collect::<Vec<_>>(),
+        [format!("{:?}", Event::WindowClosed(WindowId::MAIN))]
+    );
+}
+
+#[test]
+fn run_headless_exits_when_the_app_requests_it() {
+    AppBuilder::new()
+        .add_listener(|app: &AppHandle, event: &Event| {
+            if matches!(event, Event::Ready) {
+                app.request_exit().unwrap();
+            }
+        })
+        .build()
+        .unwrap()
+        .run_headless(|backend| {
+            let start = Instant::now();
+            while backend.step() {
+                assert!(start.elapsed() < TIMEOUT, "app did not exit");
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            assert!(backend.is_exiting());
+            assert!(backend.windows().is_empty());
+        })
+        .unwrap();
+}
@@ -0,0 +1,170 @@
+use brul::{
+    AppBuilder, AppHandle, AppManager, Event,
+    util::{Error, WindowId},
+};
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+#[test]
+fn run_emits_lifecycle_and_honors_prevent_exit() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+
+    AppBuilder::new()
+        .headless()
+        .add_listener({
+            let log = Arc::clone(&log);
+            move |app: &AppHandle, event: &Event| {
+                let mut log = log.lock().unwrap();
+                match event {
+                    Event::Ready => {
+                        log.push("ready");
+                        app.close_window(WindowId::MAIN).unwrap();
+                    }
+                    Event::BeforeExit(request) => {
+                        log.push("before-exit");
+                        // The vetoed close keeps the main window, the next exit request goes
+                        // through.
+                        if app.windows().contains(WindowId::MAIN) {
+                            log.push("main-open");
+                        }
+                        if log.iter().filter(|entry| **entry == "before-exit").count() == 1 {
+                            request.prevent_exit();
+                            app.request_exit().unwrap();
+                        }
+                    }
+                    Event::AppStarted => log.push("started"),
+                    Event::Resumed => log.push("resumed"),
+                    Event::WindowCreated(_) => log.push("window-created"),
+                    Event::WindowClosed(_) => log.push("window-closed"),
+                    Event::AppShutdown => log.push("exited"),
+                    _ => {}
+                }
+            }
+        })
+        .run()
+        .unwrap();
+
+    assert_eq!(
+        *log.lock().unwrap(),
+        [
+            "started",
+            "resumed",
+            "window-created",
+            "ready",
+            "before-exit",
+            "main-open",
+            "before-exit",
+            "main-open",
+            "window-closed",
+            "exited",
+        ]
+    );
+}
+
+#[test]
+fn run_gives_tasks_a_grace_period_and_drops_stuck_ones() {
+    let cleaned_up = Arc::new(AtomicBool::new(false));
+    let handle = Arc::new(Mutex::new(None));
+
+    let start = Instant::now();
+    let report = AppBuilder::new()
+        .headless()
+        .shutdown_grace_period(Duration::from_millis(200))
+        .add_listener({
+            let cleaned_up = Arc::clone(&cleaned_up);
+            let handle = Arc::clone(&handle);
+            move |app: &AppHandle, event: &Event| {
+                if !matches!(event, Event::Ready) {
+                    return;
+                }
+                *handle.lock().unwrap() = Some(app.clone());
+
+                let token = app.shutdown_token();
+                let cleaned_up = Arc::clone(&cleaned_up);
+                app.spawn(async move {
+                    token.cancelled().await;
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                    cleaned_up.store(true, Ordering::SeqCst);
+                });
+                // Ignores the shutdown token, dropped once the grace period is over.
+                app.spawn(std::future::pending());
+                app.request_exit().unwrap();
+            }
+        })
+        .run()
+        .unwrap();
+
+    assert!(cleaned_up.load(Ordering::SeqCst));
+    assert!(start.elapsed() < Duration::from_secs(5));
+    // Only the pending task is reported, with the location it was spawned at.
+    let [stuck] = report.stuck_tasks() else {
+        panic!("expected one stuck task, got {:?}", report.stuck_tasks());
+    };
+    assert_eq!(stuck.file(), file!());
+    assert!(!report.is_clean());
+    let handle = handle.lock().unwrap().take().unwrap();
+    assert!(matches!(handle.request_exit(), Err(Error::AppNotRunning)));
+}
+
+struct Ping;
+
+#[brul::command]
+async fn exit_then_finish(app: AppHandle) -> bool {
+    app.event_bus().emit(Ping);
+    app.request_exit().unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    true
+}
+
+#[test]
+fn run_waits_for_commands_and_async_listeners() {
+    let listened = Arc::new(AtomicBool::new(false));
+    let invoking = Arc::new(Mutex::new(None));
+
+    let report = AppBuilder::new()
+        .headless()
+        .invoke_handler(brul::generate_handlers![exit_then_finish])
+        .setup({
+            let listened = Arc::clone(&listened);
+            move |app| {
+                app.app_handle()
+                    .event_bus()
+                    .listen_async(move |_: Arc<Ping>| {
+                        let listened = Arc::clone(&listened);
+                        async move {
+                            tokio::time::sleep(Duration::from_millis(50)).await;
+                            listened.store(true, Ordering::SeqCst);
+                        }
+                    });
+            }
+        })
+        .add_listener({
+            let invoking = Arc::clone(&invoking);
+            move |app: &AppHandle, event: &Event| {
+                if !matches!(event, Event::Ready) {
+                    return;
+                }
+                // Invoked from outside the app, so only the command itself is an app task.
+                let app = app.clone();
+                *invoking.lock().unwrap() = Some(std::thread::spawn(move || {
+                    tokio::runtime::Builder::new_current_thread()
+                        .enable_all()
+                        .build()
+                        .unwrap()
+                        .block_on(app.invoke("exit_then_finish", serde_json::Value::Null))
+                }));
+            }
+        })
+        .run()
+        .unwrap();
+
+    assert!(report.is_clean());
+    assert!(listened.load(Ordering::SeqCst));
+    let invoking = invoking.lock().unwrap().take().unwrap();
+    assert_eq!(invoking.join().unwrap().unwrap(), true);
+}
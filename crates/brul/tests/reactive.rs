@@ -0,0 +1,300 @@
+use brul::reactive::{Effect, Memo, Signal, batch, untrack};
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+        mpsc,
+    },
+    time::Duration,
+};
+
+#[test]
+fn effect_reruns_on_change() {
+    let count = Signal::new(1);
+    let seen = Arc::new(Mutex::new(Vec::new()));
+
+    let _effect = Effect::new({
+        let count = count.clone();
+        let seen = Arc::clone(&seen);
+        move || seen.lock().unwrap().push(count.get())
+    });
+    count.set(2);
+    count.set(3);
+
+    assert_eq!(*seen.lock().unwrap(), [1, 2, 3]);
+}
+
+#[test]
+fn memo_skips_unchanged_results() {
+    let count = Signal::new(1);
+    let runs = Arc::new(AtomicUsize::new(0));
+
+    let parity = Memo::new({
+        let count = count.clone();
+        move || count.get() % 2
+    });
+    let _effect = Effect::new({
+        let parity = parity.clone();
+        let runs = Arc::clone(&runs);
+        move || {
+            parity.get();
+            runs.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+    count.set(3);
+    assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+    count.set(4);
+    assert_eq!(runs.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn diamond_is_glitch_free() {
+    let a = Signal::new(1);
+    let b = Memo::new({
+        let a = a.clone();
+        move || a.get() * 2
+    });
+    let c = Memo::new({
+        let a = a.clone();
+        move || a.get() * 3
+    });
+    let seen = Arc::new(Mutex::new(Vec::new()));
+
+    let _effect = Effect::new({
+        let seen = Arc::clone(&seen);
+        move || seen.lock().unwrap().push((b.get(), c.get()))
+    });
+    a.set(2);
+
+    assert_eq!(*seen.lock().unwrap(), [(2, 3), (4, 6)]);
+}
+
+#[test]
+fn batch_runs_effects_once() {
+    let first = Signal::new("a");
+    let last = Signal::new("b");
+    let runs = Arc::new(AtomicUsize::new(0));
+
+    let _effect = Effect::new({
+        let (first, last) = (first.clone(), last.clone());
+        let runs = Arc::clone(&runs);
+        move || {
+            first.get();
+            last.get();
+            runs.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+    batch(|| {
+        first.set("c");
+        last.set("d");
+    });
+
+    assert_eq!(runs.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn untracked_reads_and_dropped_effects_do_not_rerun() {
+    let tracked = Signal::new(0);
+    let ignored = Signal::new(0);
+    let runs = Arc::new(AtomicUsize::new(0));
+
+    let effect = Effect::new({
+        let (tracked, ignored) = (tracked.clone(), ignored.clone());
+        let runs = Arc::clone(&runs);
+        move || {
+            tracked.get();
+            untrack(|| ignored.get());
+            runs.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+    ignored.set(1);
+    assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+    effect.dispose();
+    tracked.set(1);
+    assert_eq!(runs.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn writes_from_other_threads_run_effects() {
+    let count = Signal::new(0);
+    let seen = Arc::new(Mutex::new(Vec::new()));
+
+    let _effect = Effect::new({
+        let count = count.clone();
+        let seen = Arc::clone(&seen);
+        move || seen.lock().unwrap().push(count.get())
+    });
+    std::thread::spawn(move || count.set(5)).join().unwrap();
+
+    assert_eq!(*seen.lock().unwrap(), [0, 5]);
+}
+
+#[test]
+fn other_threads_do_not_flush_an_open_batch() {
+    let batched = Signal::new(0);
+    let other = Signal::new(0);
+    let runs = Arc::new(AtomicUsize::new(0));
+
+    let _effect = Effect::new({
+        let batched = batched.clone();
+        let runs = Arc::clone(&runs);
+        move || {
+            batched.get();
+            runs.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+    let _other_effect = Effect::new({
+        let other = other.clone();
+        move || {
+            other.get();
+        }
+    });
+    batch(|| {
+        batched.set(1);
+        std::thread::spawn(move || other.set(1)).join().unwrap();
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+    });
+
+    assert_eq!(runs.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn panicking_batch_does_not_stay_open() {
+    let count = Signal::new(0);
+    let runs = Arc::new(AtomicUsize::new(0));
+
+    let _effect = Effect::new({
+        let count = count.clone();
+        let runs = Arc::clone(&runs);
+        move || {
+            count.get();
+            runs.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+    let result = std::panic::catch_unwind(|| batch(|| panic!("write failed")));
+    assert!(result.is_err());
+    count.set(1);
+
+    assert_eq!(runs.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn panicking_effect_does_not_stop_the_batch() {
+    let count = Signal::new(0);
+    let failing_runs = Arc::new(AtomicUsize::new(0));
+    let other_runs = Arc::new(AtomicUsize::new(0));
+
+    let _failing = Effect::new({
+        let count = count.clone();
+        let runs = Arc::clone(&failing_runs);
+        move || {
+            runs.fetch_add(1, Ordering::SeqCst);
+            assert_ne!(count.get(), 1, "effect failed");
+        }
+    });
+    let _other = Effect::new({
+        let count = count.clone();
+        let runs = Arc::clone(&other_runs);
+        move || {
+            count.get();
+            runs.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+
+    let result = std::panic::catch_unwind(|| count.set(1));
+    assert!(result.is_err());
+    assert_eq!(other_runs.load(Ordering::SeqCst), 2);
+
+    // The failed effect is still subscribed.
+    count.set(2);
+    assert_eq!(failing_runs.load(Ordering::SeqCst), 3);
+    assert_eq!(other_runs.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn panicking_memo_recovers_after_the_next_change() {
+    let count = Signal::new(1);
+    let memo = Memo::new({
+        let count = count.clone();
+        move || {
+            let value = count.get();
+            assert_ne!(value, 1, "memo failed");
+            value * 2
+        }
+    });
+
+    assert!(std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| memo.get())).is_err());
+    count.set(2);
+    assert_eq!(memo.get(), 4);
+}
+
+/// Effect that records what it reads through `read` and, while handling 1, lets another
+/// thread write 2 before it finishes.
+fn effect_racing_a_writer(
+    count: &Signal<i32>,
+    read: impl Fn() -> i32 + Send + Sync + 'static,
+) -> (Effect, Arc<Mutex<Vec<i32>>>, std::thread::JoinHandle<()>) {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let (started_tx, started_rx) = mpsc::channel();
+    let (written_tx, written_rx) = mpsc::channel();
+    let written_rx = Mutex::new(written_rx);
+
+    let effect = Effect::new({
+        let seen = Arc::clone(&seen);
+        move || {
+            let value = read();
+            if value == 1 {
+                started_tx.send(()).unwrap();
+                written_rx
+                    .lock()
+                    .unwrap()
+                    .recv_timeout(Duration::from_secs(5))
+                    .unwrap();
+            }
+            seen.lock().unwrap().push(value);
+        }
+    });
+    let writer = std::thread::spawn({
+        let count = count.clone();
+        move || {
+            started_rx.recv().unwrap();
+            count.set(2);
+            written_tx.send(()).unwrap();
+        }
+    });
+    (effect, seen, writer)
+}
+
+#[test]
+fn writes_from_another_thread_while_an_effect_runs_are_not_lost() {
+    let count = Signal::new(0);
+    let (_effect, seen, writer) = effect_racing_a_writer(&count, {
+        let count = count.clone();
+        move || count.get()
+    });
+
+    count.set(1);
+    writer.join().unwrap();
+
+    assert_eq!(*seen.lock().unwrap(), [0, 1, 2]);
+}
+
+#[test]
+fn writes_from_another_thread_reach_effects_through_memos() {
+    let count = Signal::new(0);
+    let doubled = Memo::new({
+        let count = count.clone();
+        move || count.get() * 2
+    });
+    let (_effect, seen, writer) = effect_racing_a_writer(&count, move || doubled.get() / 2);
+
+    count.set(1);
+    writer.join().unwrap();
+    assert_eq!(*seen.lock().unwrap(), [0, 1, 2]);
+
+    // The memo is still observed after the race.
+    count.set(3);
+    assert_eq!(*seen.lock().unwrap(), [0, 1, 2, 3]);
+}
@@ -0,0 +1,70 @@
+use brul::{AppBuilder, AppHandle, AppManager, Event, RuntimeConfig, util::Error};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Runs a headless app that spawns one task on `Ready` and exits once it finished; returns
+/// the name of the thread the task ran on.
+fn task_thread_name(runtime: RuntimeConfig) -> Option<String> {
+    let name = Arc::new(Mutex::new(None));
+    AppBuilder::new()
+        .headless()
+        .runtime(runtime)
+        .add_listener({
+            let name = Arc::clone(&name);
+            move |app: &AppHandle, event: &Event| {
+                if !matches!(event, Event::Ready) {
+                    return;
+                }
+                let name = Arc::clone(&name);
+                let app = app.clone();
+                app.clone().spawn(async move {
+                    tokio::time::sleep(Duration::from_millis(1)).await;
+                    *name.lock().unwrap() = std::thread::current().name().map(str::to_owned);
+                    app.request_exit().unwrap();
+                });
+            }
+        })
+        .run()
+        .unwrap();
+    name.lock().unwrap().take()
+}
+
+#[test]
+fn tasks_run_on_the_configured_runtime() {
+    let name = task_thread_name(
+        RuntimeConfig::multi_thread()
+            .worker_threads(1)
+            .thread_name("app-worker"),
+    );
+    assert_eq!(name.as_deref(), Some("app-worker"));
+
+    let name = task_thread_name(RuntimeConfig::current_thread().thread_name("app-main"));
+    assert_eq!(name.as_deref(), Some("app-main"));
+}
+
+#[test]
+fn an_existing_runtime_is_adopted_and_kept_running() {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(1)
+        .thread_name("host-worker")
+        .enable_all()
+        .build()
+        .unwrap();
+
+    let name = task_thread_name(RuntimeConfig::existing(runtime.handle().clone()));
+    assert_eq!(name.as_deref(), Some("host-worker"));
+    assert_eq!(runtime.block_on(async { 1 + 1 }), 2);
+}
+
+#[test]
+fn invalid_runtime_config_is_reported() {
+    for runtime in [
+        RuntimeConfig::multi_thread().worker_threads(0),
+        RuntimeConfig::current_thread().worker_threads(2),
+    ] {
+        let result = AppBuilder::new().headless().runtime(runtime).build();
+        assert!(matches!(result, Err(Error::InvalidRuntimeConfig(_))));
+    }
+}
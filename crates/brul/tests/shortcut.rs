@@ -0,0 +1,227 @@
+use brul::{
+    AppBuilder, AppHandle, AppManager, Error, State,
+    command::Cancellation,
+    headless::HeadlessBackend,
+    input::{InputEvent, Key, KeyCode, KeyEvent, Modifiers, NamedKey},
+    shortcut::{ShortcutAction, ShortcutScope},
+    util::{WindowConfig, WindowId},
+};
+use std::{
+    sync::{Arc, Mutex, mpsc},
+    thread,
+    time::{Duration, Instant},
+};
+use tokio_util::sync::CancellationToken;
+
+fn key_down(key: &str, code: Option<KeyCode>, modifiers: Modifiers) -> InputEvent {
+    InputEvent::KeyDown(KeyEvent {
+        key: Key::Character(key.into()),
+        physical_key: code,
+        modifiers,
+        repeat: false,
+    })
+}
+
+fn ctrl() -> Modifiers {
+    Modifiers {
+        control: true,
+        ..Default::default()
+    }
+}
+
+fn recorder(log: &Arc<Mutex<Vec<String>>>, name: &'static str) -> ShortcutAction {
+    let log = Arc::clone(log);
+    ShortcutAction::callback(move |_, window| {
+        log.lock().unwrap().push(format!("{name}@{window:?}"));
+    })
+}
+
+#[test]
+fn parses_and_detects_conflicts() {
+    let app = AppBuilder::new().build().unwrap();
+    let shortcuts = app.app_handle().shortcuts();
+    let noop = || ShortcutAction::callback(|_, _| {});
+
+    shortcuts
+        .register("Ctrl+K Ctrl+S", ShortcutScope::Global, noop())
+        .unwrap();
+    assert!(matches!(
+        shortcuts.register("ctrl+k", ShortcutScope::Global, noop()),
+        Err(Error::ShortcutConflict { existing, .. }) if existing == "Ctrl+K Ctrl+S"
+    ));
+    // Another scope may shadow the global binding.
+    shortcuts
+        .register("Ctrl+K", WindowId::MAIN, noop())
+        .unwrap();
+    assert!(matches!(
+        shortcuts.register("Ctrl+Hyper+K", ShortcutScope::Global, noop()),
+        Err(Error::InvalidShortcut(_))
+    ));
+    assert!(matches!(
+        AppBuilder::new()
+            .shortcut("Ctrl+P", noop())
+            .shortcut("Control+p", noop())
+            .build(),
+        Err(Error::ShortcutConflict { .. })
+    ));
+}
+
+#[test]
+fn runs_multi_stroke_and_scoped_shortcuts() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let other = WindowId::next();
+    let app = AppBuilder::new()
+        .shortcut("Ctrl+K Ctrl+S", recorder(&log, "save-all"))
+        .shortcut("Ctrl+Shift+1", recorder(&log, "tab"))
+        .build()
+        .unwrap();
+    let handle: &AppHandle = app.app_handle();
+    let shortcuts = handle.shortcuts();
+    shortcuts
+        .register("Ctrl+S", other, recorder(&log, "save"))
+        .unwrap();
+
+    let press = |window, event: InputEvent| shortcuts.handle_input(handle, window, &event);
+    press(WindowId::MAIN, key_down("k", Some(KeyCode::KeyK), ctrl()));
+    press(WindowId::MAIN, key_down("s", Some(KeyCode::KeyS), ctrl()));
+    // Window scoped, so only the other window saves.
+    press(WindowId::MAIN, key_down("s", Some(KeyCode::KeyS), ctrl()));
+    press(other, key_down("s", Some(KeyCode::KeyS), ctrl()));
+    // A broken sequence starts over with the last key.
+    press(other, key_down("k", Some(KeyCode::KeyK), ctrl()));
+    press(other, key_down("x", Some(KeyCode::KeyX), ctrl()));
+    press(other, key_down("s", Some(KeyCode::KeyS), ctrl()));
+    // Shift changes the logical key, the physical key still matches.
+    let ctrl_shift = Modifiers {
+        shift: true,
+        ..ctrl()
+    };
+    press(other, key_down("!", Some(KeyCode::Digit1), ctrl_shift));
+
+    assert_eq!(
+        *log.lock().unwrap(),
+        [
+            format!("save-all@{:?}", WindowId::MAIN),
+            format!("save@{other:?}"),
+            format!("save@{other:?}"),
+            format!("tab@{other:?}"),
+        ]
+    );
+}
+
+#[test]
+fn escape_quits_only_when_opted_in() {
+    let app = AppBuilder::new().build().unwrap();
+    assert!(
+        app.app_handle()
+            .shortcuts()
+            .shortcuts(WindowId::MAIN)
+            .is_empty()
+    );
+
+    let app = AppBuilder::new().quit_on_escape().build().unwrap();
+    let shortcuts = app.app_handle().shortcuts().shortcuts(WindowId::MAIN);
+    assert_eq!(shortcuts.len(), 1);
+    assert_eq!(shortcuts[0].1.chords()[0].key, Key::Named(NamedKey::Escape));
+}
+
+#[test]
+fn letters_follow_the_layout_not_the_key_position() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let app = AppBuilder::new()
+        .shortcut("Ctrl+A", recorder(&log, "select-all"))
+        .shortcut("Ctrl+Shift+A", recorder(&log, "shifted"))
+        .build()
+        .unwrap();
+    let handle: &AppHandle = app.app_handle();
+    let shortcuts = handle.shortcuts();
+    let press = |event: InputEvent| shortcuts.handle_input(handle, WindowId::MAIN, &event);
+    let ctrl_shift = Modifiers {
+        shift: true,
+        ..ctrl()
+    };
+
+    // On AZERTY the key in the US position of A types "q", and the one of Q types "a".
+    press(key_down("q", Some(KeyCode::KeyA), ctrl()));
+    press(key_down("Q", Some(KeyCode::KeyA), ctrl_shift));
+    assert!(log.lock().unwrap().is_empty());
+    press(key_down("a", Some(KeyCode::KeyQ), ctrl()));
+    press(key_down("A", Some(KeyCode::KeyQ), ctrl_shift));
+
+    assert_eq!(
+        *log.lock().unwrap(),
+        [
+            format!("select-all@{:?}", WindowId::MAIN),
+            format!("shifted@{:?}", WindowId::MAIN),
+        ]
+    );
+}
+
+/// Token of the running `wait_for_close` command.
+#[derive(Default)]
+struct Running(Mutex<Option<CancellationToken>>);
+
+#[brul::command]
+async fn wait_for_close(running: State<Running>, cancellation: Cancellation) {
+    *running.0.lock().unwrap() = Some(cancellation.into_token());
+    std::future::pending().await
+}
+
+/// Steps the backend until `condition` returns a value.
+fn step_until<T>(backend: &mut HeadlessBackend, mut condition: impl FnMut() -> Option<T>) -> T {
+    let start = Instant::now();
+    loop {
+        backend.step();
+        if let Some(value) = condition() {
+            return value;
+        }
+        assert!(
+            start.elapsed() < Duration::from_secs(5),
+            "condition never held"
+        );
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+#[test]
+fn shortcut_commands_are_cancelled_with_their_window() {
+    let app = AppBuilder::new()
+        .manage(Running::default())
+        .invoke_handler(brul::generate_handlers![wait_for_close])
+        .build()
+        .unwrap();
+    let handle = app.app_handle().clone();
+
+    app.run_headless(|backend| {
+        let (tx, rx) = mpsc::channel();
+        handle.spawn({
+            let handle = handle.clone();
+            async move {
+                let _ = tx.send(handle.create_window(WindowConfig::new("Second")).await);
+            }
+        });
+        let window = step_until(backend, || rx.try_recv().ok()).unwrap();
+        handle
+            .shortcuts()
+            .register(
+                "Ctrl+W",
+                window.id(),
+                ShortcutAction::command("wait_for_close"),
+            )
+            .unwrap();
+
+        handle.shortcuts().handle_input(
+            &handle,
+            window.id(),
+            &key_down("w", Some(KeyCode::KeyW), ctrl()),
+        );
+        let token = step_until(backend, || {
+            handle.state::<Running>().0.lock().unwrap().clone()
+        });
+        assert!(!token.is_cancelled());
+
+        window.close().unwrap();
+        step_until(backend, || token.is_cancelled().then_some(()));
+    })
+    .unwrap();
+}
@@ -0,0 +1,239 @@
+use brul::{AppBuilder, AppHandle, AppManager, Error, Event, EventDiscriminants, reactive::Effect};
+use std::{
+    any::TypeId,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+    },
+};
+
+#[derive(Debug, Clone, PartialEq)]
+struct Counter(u32);
+
+#[derive(Debug, PartialEq)]
+struct Name(&'static str);
+
+#[test]
+fn builder_manage_registers_concrete_type() {
+    let app = AppBuilder::new()
+        .manage(Counter(1))
+        .manage(Name("brul"))
+        .build()
+        .unwrap();
+
+    assert_eq!(*app.state::<Counter>(), Counter(1));
+    assert_eq!(*app.state::<Name>(), Name("brul"));
+    assert!(app.try_state::<u8>().is_none());
+}
+
+#[test]
+fn builder_duplicate_manage_is_an_error() {
+    let result = AppBuilder::new()
+        .manage(Counter(1))
+        .manage(Counter(2))
+        .build();
+
+    match result {
+        Err(Error::StateAlreadyManaged(name)) => assert!(name.ends_with("Counter")),
+        Err(err) => panic!("unexpected error: {err}"),
+        Ok(_) => panic!("duplicate state was accepted"),
+    }
+}
+
+#[test]
+fn setup_hook_manage_sees_builder_state() {
+    let app = AppBuilder::new()
+        .manage(Counter(1))
+        .setup(|app| {
+            assert!(!app.manage(Counter(2)));
+            assert!(app.manage(Name("setup")));
+        })
+        .build()
+        .unwrap();
+
+    assert_eq!(*app.state::<Counter>(), Counter(1));
+    assert_eq!(*app.state::<Name>(), Name("setup"));
+}
+
+#[test]
+fn handle_manage_is_shared_with_app() {
+    let app = AppBuilder::new().build().unwrap();
+    let mut handle = app.app_handle().clone();
+
+    assert!(handle.manage(Counter(7)));
+    assert!(!handle.manage(Counter(8)));
+    assert_eq!(*app.state::<Counter>(), Counter(7));
+}
+
+#[test]
+fn state_handles_are_owned() {
+    let app = AppBuilder::new().manage(Counter(3)).build().unwrap();
+    let state = app.state::<Counter>();
+
+    let value = std::thread::spawn(move || state.0).join().unwrap();
+    assert_eq!(value, 3);
+}
+
+#[test]
+fn update_notifies_listeners_and_watchers() {
+    let app = AppBuilder::new().manage(Counter(0)).build().unwrap();
+    let events = Arc::new(AtomicUsize::new(0));
+    app.app_handle()
+        .event_bus()
+        .subscribe(EventDiscriminants::StateChanged, {
+            let events = Arc::clone(&events);
+            move |_| {
+                events.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+    let revision = Arc::new(AtomicU64::new(0));
+    let _effect = Effect::new({
+        let watch = app.watch_state::<Counter>();
+        let revision = Arc::clone(&revision);
+        move || revision.store(watch.get(), Ordering::SeqCst)
+    });
+
+    let before = app.state::<Counter>();
+    app.update::<Counter, _>(|counter| counter.0 += 1);
+    app.state_mut::<Counter>().0 += 1;
+
+    assert_eq!(*before, Counter(0));
+    assert_eq!(*app.state::<Counter>(), Counter(2));
+    assert_eq!(events.load(Ordering::SeqCst), 2);
+    assert_eq!(revision.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn replace_and_unmanage() {
+    let app = AppBuilder::new().build().unwrap();
+
+    assert!(app.replace(Counter(1)).is_none());
+    assert_eq!(app.replace(Counter(2)).as_deref(), Some(&Counter(1)));
+    assert_eq!(app.unmanage::<Counter>().as_deref(), Some(&Counter(2)));
+    assert!(app.try_state::<Counter>().is_none());
+    assert!(app.unmanage::<Counter>().is_none());
+}
+
+#[test]
+fn state_changed_names_the_updated_type() {
+    let changed = Arc::new(Mutex::new(Vec::new()));
+    let app = AppBuilder::new()
+        .manage(Counter(0))
+        .manage(Name("brul"))
+        .add_listener({
+            let changed = Arc::clone(&changed);
+            move |_: &AppHandle, event: &Event| {
+                if let Event::StateChanged { type_id, type_name } = event {
+                    changed.lock().unwrap().push((*type_id, *type_name));
+                }
+            }
+        })
+        .build()
+        .unwrap();
+
+    {
+        let mut counter = app.state_mut::<Counter>();
+        counter.0 += 1;
+        counter.0 += 1;
+        // Listeners only hear about the change once the guard is dropped.
+        assert!(changed.lock().unwrap().is_empty());
+    }
+    assert_eq!(app.update::<Counter, _>(|counter| counter.0), 2);
+
+    let changed = changed.lock().unwrap();
+    assert_eq!(changed.len(), 2);
+    assert!(
+        changed
+            .iter()
+            .all(|(id, name)| *id == TypeId::of::<Counter>() && name.ends_with("Counter"))
+    );
+}
+
+#[test]
+fn watch_state_only_tracks_its_own_type() {
+    let app = AppBuilder::new()
+        .manage(Counter(0))
+        .manage(Name("brul"))
+        .build()
+        .unwrap();
+    let runs = Arc::new(AtomicUsize::new(0));
+    let _effect = Effect::new({
+        let watch = app.watch_state::<Counter>();
+        let runs = Arc::clone(&runs);
+        move || {
+            watch.get();
+            runs.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+
+    app.replace(Name("other"));
+    assert_eq!(runs.load(Ordering::SeqCst), 1);
+    app.update::<Counter, _>(|counter| counter.0 = 5);
+    assert_eq!(runs.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn try_state_mut_of_unmanaged_state_is_none() {
+    let app = AppBuilder::new().build().unwrap();
+
+    assert!(app.try_state_mut::<Counter>().is_none());
+}
+
+static CLONES: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, PartialEq)]
+struct Tracked(u32);
+
+impl Clone for Tracked {
+    fn clone(&self) -> Self {
+        CLONES.fetch_add(1, Ordering::SeqCst);
+        Tracked(self.0)
+    }
+}
+
+#[test]
+fn writes_clone_only_while_snapshots_are_alive() {
+    let app = AppBuilder::new().manage(Tracked(0)).build().unwrap();
+
+    app.update::<Tracked, _>(|tracked| tracked.0 += 1);
+    assert_eq!(CLONES.load(Ordering::SeqCst), 0);
+
+    let snapshot = app.state::<Tracked>();
+    {
+        let mut tracked = app.state_mut::<Tracked>();
+        tracked.0 += 1;
+        tracked.0 += 1;
+    }
+    assert_eq!(CLONES.load(Ordering::SeqCst), 1);
+    // The handle keeps the value it was taken with.
+    assert_eq!(*snapshot, Tracked(1));
+    assert_eq!(*app.state::<Tracked>(), Tracked(3));
+
+    drop(snapshot);
+    app.update::<Tracked, _>(|tracked| tracked.0 += 1);
+    assert_eq!(CLONES.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn read_only_state_mut_notifies_nobody() {
+    let changed = Arc::new(AtomicUsize::new(0));
+    let app = AppBuilder::new()
+        .manage(Counter(4))
+        .add_listener({
+            let changed = Arc::clone(&changed);
+            move |_: &AppHandle, event: &Event| {
+                if matches!(event, Event::StateChanged { .. }) {
+                    changed.fetch_add(1, Ordering::SeqCst);
+                }
+            }
+        })
+        .build()
+        .unwrap();
+    let watch = app.watch_state::<Counter>();
+    let revision = watch.get();
+
+    assert_eq!(app.state_mut::<Counter>().0, 4);
+
+    assert_eq!(changed.load(Ordering::SeqCst), 0);
+    assert_eq!(watch.get(), revision);
+}
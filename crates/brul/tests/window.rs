@@ -0,0 +1,254 @@
+use brul::{
+    AppBuilder, AppHandle, AppManager, Event, State,
+    headless::{HeadlessBackend, SyntheticEvent},
+    util::{Color, Error, Size, WindowConfig, WindowId, WindowSize},
+};
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Runs `future` on the app runtime and steps the backend until it resolves, so requests
+/// sent to the GUI thread get answered.
+fn step_until<T: Send + 'static>(
+    backend: &mut HeadlessBackend,
+    app: &AppHandle,
+    future: impl Future<Output = T> + Send + 'static,
+) -> T {
+    let (tx, rx) = mpsc::channel();
+    app.spawn(async move {
+        let _ = tx.send(future.await);
+    });
+    let start = Instant::now();
+    loop {
+        backend.step();
+        if let Ok(value) = rx.try_recv() {
+            return value;
+        }
+        assert!(start.elapsed() < TIMEOUT, "request was not answered");
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+fn dims(size: WindowSize) -> (u32, u32) {
+    (size.width, size.height)
+}
+
+/// Steps the backend until the app side caught up with it.
+fn step_while(backend: &mut HeadlessBackend, mut condition: impl FnMut() -> bool) {
+    let start = Instant::now();
+    while condition() {
+        backend.step();
+        assert!(start.elapsed() < TIMEOUT, "condition still holds");
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+#[test]
+fn windows_are_created_updated_and_closed() {
+    let app = AppBuilder::new().build().unwrap();
+    let handle = app.app_handle().clone();
+
+    app.run_headless(|backend| {
+        backend.step();
+        step_while(backend, || handle.windows().size(WindowId::MAIN).is_none());
+        let main = handle.main_window().unwrap();
+        assert_eq!(dims(main.size().unwrap()), (800, 600));
+        assert_eq!(handle.window_size(), main.size());
+        // Windows are cleared with magenta unless configured otherwise.
+        assert_eq!(
+            backend.config(WindowId::MAIN).unwrap().background_color,
+            Color::rgb(1.0, 0.0, 1.0)
+        );
+
+        let config = WindowConfig {
+            inner_size: Size::new(200.0, 100.0),
+            ..WindowConfig::new("Second")
+        };
+        let window = step_until(backend, &handle, {
+            let handle = handle.clone();
+            async move { handle.create_window(config).await }
+        })
+        .unwrap();
+        let id = window.id();
+        assert_eq!(backend.windows(), [WindowId::MAIN, id]);
+        assert_eq!(backend.config(id).unwrap().title, "Second");
+        assert_eq!(dims(window.size().unwrap()), (200, 100));
+
+        step_until(backend, &handle, {
+            let window = window.clone();
+            async move { window.set_title("Renamed").await }
+        })
+        .unwrap();
+        window.set_inner_size(Size::new(300.0, 150.0)).unwrap();
+        window.set_background_color(Color::WHITE).unwrap();
+        backend.step();
+        assert_eq!(backend.config(id).unwrap().title, "Renamed");
+        assert_eq!(window.config().unwrap().title, "Renamed");
+        assert_eq!(dims(backend.size(id).unwrap()), (300, 150));
+        assert_eq!(backend.config(id).unwrap().background_color, Color::WHITE);
+        assert_eq!(window.config().unwrap().inner_size, Size::new(300.0, 150.0));
+        let size = step_until(backend, &handle, {
+            let window = window.clone();
+            async move { window.inner_size().await }
+        })
+        .unwrap();
+        assert_eq!(dims(size), (300, 150));
+
+        // Resizes coming from the platform reach the cached size.
+        backend.inject(
+            id,
+            SyntheticEvent::Resized {
+                width: 320,
+                height: 160,
+            },
+        );
+        step_while(backend, || dims(window.size().unwrap()) != (320, 160));
+
+        window.close().unwrap();
+        step_while(backend, || handle.windows().contains(id));
+        assert_eq!(backend.windows(), [WindowId::MAIN]);
+        assert!(handle.get_window(id).is_none());
+        assert!(!backend.is_exiting());
+    })
+    .unwrap();
+}
+
+#[test]
+fn requests_for_unknown_windows_fail() {
+    let app = AppBuilder::new().build().unwrap();
+    let handle = app.app_handle().clone();
+
+    app.run_headless(|backend| {
+        let unknown = WindowId::next();
+        let result = step_until(backend, &handle, {
+            let handle = handle.clone();
+            async move { handle.inner_size(unknown).await }
+        });
+        assert!(matches!(result, Err(Error::WindowNotFound(id)) if id == unknown));
+
+        let result = step_until(backend, &handle, {
+            let handle = handle.clone();
+            async move { handle.request_redraw(unknown).await }
+        });
+        assert!(matches!(result, Err(Error::WindowNotFound(_))));
+
+        let result = step_until(backend, &handle, {
+            let handle = handle.clone();
+            async move { handle.set_title(unknown, "Lost").await }
+        });
+        assert!(matches!(result, Err(Error::WindowNotFound(_))));
+        assert!(handle.windows().config(unknown).is_none());
+    })
+    .unwrap();
+}
+
+#[test]
+fn closing_the_main_window_exits_with_other_windows_open() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let mut second = None;
+    let app = AppBuilder::new()
+        .add_listener({
+            let log = Arc::clone(&log);
+            move |_: &AppHandle, event: &Event| match event {
+                Event::BeforeExit(_) => log.lock().unwrap().push("before-exit".to_owned()),
+                Event::WindowClosed(id) => log.lock().unwrap().push(format!("closed {id:?}")),
+                _ => {}
+            }
+        })
+        .build()
+        .unwrap();
+    let handle = app.app_handle().clone();
+
+    app.run_headless(|backend| {
+        backend.step();
+        let window = step_until(backend, &handle, {
+            let handle = handle.clone();
+            async move { handle.create_window(WindowConfig::new("Second")).await }
+        })
+        .unwrap();
+
+        backend.inject(WindowId::MAIN, SyntheticEvent::CloseRequested);
+        let start = Instant::now();
+        while backend.step() {
+            assert!(start.elapsed() < TIMEOUT, "app did not exit");
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert!(backend.windows().is_empty());
+        second = Some(window.id());
+    })
+    .unwrap();
+
+    let log = log.lock().unwrap();
+    let second = second.unwrap();
+    assert_eq!(log[0], "before-exit");
+    assert!(log.contains(&format!("closed {second:?}")));
+    assert!(log.contains(&format!("closed {:?}", WindowId::MAIN)));
+}
+
+struct Started(AtomicBool);
+
+#[brul::command]
+async fn wait_for_close(started: State<Started>) -> bool {
+    started.0.store(true, Ordering::SeqCst);
+    std::future::pending().await
+}
+
+#[test]
+fn closing_a_window_cancels_its_commands() {
+    let app = AppBuilder::new()
+        .manage(Started(AtomicBool::new(false)))
+        .invoke_handler(brul::generate_handlers![wait_for_close])
+        .build()
+        .unwrap();
+    let handle = app.app_handle().clone();
+
+    app.run_headless(|backend| {
+        backend.step();
+        let window = step_until(backend, &handle, {
+            let handle = handle.clone();
+            async move { handle.create_window(WindowConfig::new("Second")).await }
+        })
+        .unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        handle.spawn({
+            let window = window.clone();
+            async move {
+                let _ = tx.send(
+                    window
+                        .invoke("wait_for_close", serde_json::Value::Null)
+                        .await,
+                );
+            }
+        });
+        step_while(backend, || {
+            !handle.state::<Started>().0.load(Ordering::SeqCst)
+        });
+
+        window.close().unwrap();
+        let mut result = None;
+        step_while(backend, || {
+            result = rx.try_recv().ok();
+            result.is_none()
+        });
+        assert!(
+            matches!(result.unwrap(), Err(Error::CommandCancelled(command)) if command == "wait_for_close")
+        );
+        // Invocations for the closed window do not start at all.
+        let result = step_until(backend, &handle, async move {
+            window
+                .invoke("wait_for_close", serde_json::Value::Null)
+                .await
+        });
+        assert!(matches!(result, Err(Error::CommandCancelled(_))));
+    })
+    .unwrap();
+}
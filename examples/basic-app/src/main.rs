@@ -1,4 +1,5 @@
 use brul::{AppHandle, AppManager, State, util::Color};
+use std::time::Duration;
 use tokio::time::Instant;
 
 #[derive(Debug)]
@@ -10,18 +11,28 @@ struct AppState {
 struct MyString(String);
 
 fn change_background_color(app_handle: &AppHandle) {
-    // let now = Instant::now();
-    // let app_state = app_handle.state::<AppState>();
-    // let time = now.duration_since(app_state.start_time).as_secs_f32();
-
-    // let color = Color::rgb(
-    //     (time.sin() * 0.5 + 0.5) as f32,
-    //     ((time + 2.0).sin() * 0.5 + 0.5) as f32,
-    //     ((time + 4.0).sin() * 0.5 + 0.5) as f32,
-    // );
-
-    // app_handle.set_background_color(color);
-    tracing::info!("Changing background color");
+    let app_handle = app_handle.clone();
+    app_handle.clone().spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_millis(16));
+        loop {
+            interval.tick().await;
+
+            let now = Instant::now();
+            let app_state = app_handle.state::<AppState>();
+            let time = now.duration_since(app_state.start_time).as_secs_f32();
+
+            let color = Color::rgb(
+                time.sin() * 0.5 + 0.5,
+                (time + 2.0).sin() * 0.5 + 0.5,
+                (time + 4.0).sin() * 0.5 + 0.5,
+            );
+
+            if let Err(err) = app_handle.set_background_color(color) {
+                tracing::info!("Stop changing background color: {err}");
+                break;
+            }
+        }
+    });
 }
 
 #[brul::command]
@@ -49,7 +60,8 @@ fn main() {
             start_time: Instant::now(),
         })
         .add_task(change_background_color)
-        // .add_handlers(brul::generate_handlers![log_app_state, log_string_state])
+        .quit_on_escape()
+        .invoke_handler(brul::generate_handlers![log_app_state, log_string_state])
         .run()
         .expect("Error while running brul application");
 }